@@ -0,0 +1,261 @@
+//! Integeriser that stores all values back to back in one contiguous buffer.
+
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::ffi::OsStr;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use block::Blocks;
+use storage::{ArenaRef, ArenaStorage, Storage};
+use HashIntegeriser;
+
+/// Unsized values that can be stored as a slice of bytes.
+///
+/// # Safety
+///
+/// `from_bytes(a.as_bytes())` must be a valid value equal to `a`, and two
+/// values must be equal iff their byte slices are equal.
+pub unsafe trait ArenaValue {
+    /// The bytes that represent the value.
+    fn as_bytes(&self) -> &[u8];
+
+    /// Reconstructs a value from its bytes.
+    ///
+    /// # Safety
+    ///
+    /// `bytes` must have been obtained from `as_bytes`.
+    unsafe fn from_bytes(bytes: &[u8]) -> &Self;
+}
+
+unsafe impl ArenaValue for [u8] {
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+
+    unsafe fn from_bytes(bytes: &[u8]) -> &[u8] {
+        bytes
+    }
+}
+
+unsafe impl ArenaValue for str {
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+
+    unsafe fn from_bytes(bytes: &[u8]) -> &str {
+        str::from_utf8_unchecked(bytes)
+    }
+}
+
+unsafe impl ArenaValue for OsStr {
+    fn as_bytes(&self) -> &[u8] {
+        self.as_encoded_bytes()
+    }
+
+    unsafe fn from_bytes(bytes: &[u8]) -> &OsStr {
+        OsStr::from_encoded_bytes_unchecked(bytes)
+    }
+}
+
+/// Structure that maps to every value of the unsized type `T` (such as
+/// `[u8]` or `str`) an integer of type `usize`.  Mapping goes both ways.
+/// It is a `HashIntegeriser` whose values are copied into an `ArenaStorage`,
+/// so that integerising a value allocates nothing but (amortised) room for
+/// its bytes, and whose hash map only refers to the stored bytes.
+///
+/// Since values are passed and returned by reference, this structure does
+/// not implement `Integeriser`, but has methods of the same names.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::arena::ArenaIntegeriser;
+///
+/// let mut integeriser: ArenaIntegeriser<[u8]> = ArenaIntegeriser::new();
+///
+/// let k = integeriser.integerise(&[0xca, 0xfe]);
+///
+/// assert_eq!(integeriser.integerise(&[0xbe, 0xef]), 1);
+/// assert_eq!(integeriser.integerise(&vec![0xca, 0xfe]), k);
+/// assert_eq!(integeriser.find_key(&[0xca, 0xfe]), Some(k));
+/// assert_eq!(integeriser.find_value(k), Some(&[0xca, 0xfe][..]));
+/// assert_eq!(integeriser.size(), 2);
+///
+/// let copy = integeriser.clone();
+/// drop(integeriser);
+/// assert_eq!(copy.find_key(&[0xbe, 0xef]), Some(1));
+/// ```
+pub struct ArenaIntegeriser<T: ?Sized + ArenaValue, S: BuildHasher = RandomState> {
+    inner: HashIntegeriser<ArenaRef<T>, S, ArenaStorage<T>>,
+}
+
+/// `ArenaIntegeriser` for platform strings.
+pub type OsStrIntegeriser<S = RandomState> = ArenaIntegeriser<OsStr, S>;
+
+impl<T: ?Sized + ArenaValue> ArenaIntegeriser<T, RandomState> {
+    /// Constructs a new, empty `ArenaIntegeriser<T>`.
+    pub fn new() -> Self {
+        ArenaIntegeriser::with_hasher(RandomState::new())
+    }
+}
+
+impl<T: ?Sized + ArenaValue, S: BuildHasher + Default> Default for ArenaIntegeriser<T, S> {
+    fn default() -> Self {
+        ArenaIntegeriser::with_hasher(S::default())
+    }
+}
+
+impl<T: ?Sized + ArenaValue, S: BuildHasher> ArenaIntegeriser<T, S> {
+    /// Constructs a new, empty `ArenaIntegeriser<T, S>` that hashes values with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        ArenaIntegeriser {
+            inner: HashIntegeriser {
+                map: ArenaStorage::default(),
+                rmap: HashMap::with_hasher(hash_builder),
+                epoch: 0,
+                blocks: Blocks::default(),
+            },
+        }
+    }
+
+    /// Returns a unique integer for the given value `a`, see `Integeriser::integerise`.
+    pub fn integerise(&mut self, a: &T) -> usize {
+        if let Some(k) = self.find_key(a) {
+            return k;
+        }
+
+        let inner = &mut self.inner;
+        let k = inner.map.len();
+        inner.epoch += 1;
+        let r = inner.map.alloc(a);
+        inner.rmap.insert(r, k);
+        k
+    }
+
+    /// Lookup the value that corresponds to the integer `k: usize`.
+    pub fn find_value(&self, k: usize) -> Option<&T> {
+        self.inner.map.get(k).map(ArenaRef::value)
+    }
+
+    /// Lookup the integer that corresponds to the value `a`.
+    pub fn find_key(&self, a: &T) -> Option<usize> {
+        self.inner.rmap.get(a.as_bytes()).cloned()
+    }
+
+    /// Number of distinct values that are stored in the integeriser.
+    pub fn size(&self) -> usize {
+        self.inner.map.len()
+    }
+
+    /// `ArenaStorage` containing all the values that have been stored in the integeriser.
+    pub fn values(&self) -> &ArenaStorage<T> {
+        self.inner.values()
+    }
+}
+
+impl<T: ?Sized + ArenaValue, S: BuildHasher + Clone> Clone for ArenaIntegeriser<T, S> {
+    fn clone(&self) -> Self {
+        // the hash map of the clone has to refer to the bytes of the clone
+        let mut clone = ArenaIntegeriser::with_hasher(self.inner.hasher().clone());
+        for k in 0..self.size() {
+            clone.integerise(self.find_value(k).expect("every integer below the size has a value"));
+        }
+        clone.inner.epoch = self.inner.epoch;
+        clone
+    }
+}
+
+impl<T: ?Sized + ArenaValue + fmt::Debug, S: BuildHasher> fmt::Debug for ArenaIntegeriser<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.size()).filter_map(|k| self.find_value(k)))
+            .finish()
+    }
+}
+
+/// Structure that maps to every path an integer of type `usize`, storing the
+/// paths in an `ArenaIntegeriser<OsStr>`.
+/// Paths are compared by their components, like `Path`, so `a/b`, `a//b`
+/// and `a/./b` get the same integer; every path is stored in the form that
+/// is built from its components, e.g. `a/b`.
+///
+/// # Example
+///
+/// ```
+/// use std::path::{Path, PathBuf};
+/// use integeriser::arena::PathIntegeriser;
+///
+/// let mut integeriser = PathIntegeriser::new();
+///
+/// let k = integeriser.integerise(Path::new("src//lib.rs"));
+///
+/// assert_eq!(integeriser.integerise(Path::new("src/./lib.rs")), k);
+/// assert_eq!(integeriser.find_key(&PathBuf::from("src").join("lib.rs")), Some(k));
+/// assert_eq!(integeriser.find_value(k), Some(Path::new("src/lib.rs")));
+/// assert_eq!(integeriser.find_key(Path::new("./src/lib.rs")), None);
+/// ```
+pub struct PathIntegeriser<S: BuildHasher = RandomState> {
+    inner: ArenaIntegeriser<OsStr, S>,
+}
+
+/// The path built from the components of `path`, which is equal to `path`.
+fn normalise(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+impl PathIntegeriser<RandomState> {
+    /// Constructs a new, empty `PathIntegeriser`.
+    pub fn new() -> Self {
+        PathIntegeriser::with_hasher(RandomState::new())
+    }
+}
+
+impl<S: BuildHasher + Default> Default for PathIntegeriser<S> {
+    fn default() -> Self {
+        PathIntegeriser::with_hasher(S::default())
+    }
+}
+
+impl<S: BuildHasher> PathIntegeriser<S> {
+    /// Constructs a new, empty `PathIntegeriser<S>` that hashes paths with `hash_builder`.
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PathIntegeriser { inner: ArenaIntegeriser::with_hasher(hash_builder) }
+    }
+
+    /// Returns a unique integer for the given path `a`, see `Integeriser::integerise`.
+    pub fn integerise(&mut self, a: &Path) -> usize {
+        self.inner.integerise(normalise(a).as_os_str())
+    }
+
+    /// Lookup the path that corresponds to the integer `k: usize`.
+    pub fn find_value(&self, k: usize) -> Option<&Path> {
+        self.inner.find_value(k).map(Path::new)
+    }
+
+    /// Lookup the integer that corresponds to the path `a`.
+    pub fn find_key(&self, a: &Path) -> Option<usize> {
+        self.inner.find_key(normalise(a).as_os_str())
+    }
+
+    /// Number of distinct paths that are stored in the integeriser.
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
+
+impl<S: BuildHasher + Clone> Clone for PathIntegeriser<S> {
+    fn clone(&self) -> Self {
+        PathIntegeriser { inner: self.inner.clone() }
+    }
+}
+
+impl<S: BuildHasher> fmt::Debug for PathIntegeriser<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries((0..self.size()).filter_map(|k| self.find_value(k)))
+            .finish()
+    }
+}
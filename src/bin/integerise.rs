@@ -0,0 +1,140 @@
+//! Command line interface for building and applying vocabularies.
+//! A vocabulary is stored as a journal, see `integeriser::persist`, and is
+//! compressed according to the extension of its path (`.gz` or `.zst`).
+
+extern crate integeriser;
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use integeriser::{persist, stream, HashIntegeriser, Integeriser};
+
+type Vocab = HashIntegeriser<String>;
+
+const USAGE: &str = "\
+usage: integerise <command> <vocab> [<file> …]
+
+commands:
+  build <vocab> [<file> …]  integerises the whitespace-separated tokens of the
+                            files (or of stdin) and writes the vocabulary
+  apply <vocab>             replaces every token on stdin by its id
+  encode <vocab> [--update] writes the ids of the tokens on stdin to stdout as
+                            variable-length integers; with --update, unknown
+                            tokens are added to the vocabulary
+  invert <vocab>            replaces every id on stdin by its token
+  info <vocab>              prints statistics about the vocabulary";
+
+fn read_vocab(path: &str) -> Result<Vocab, Box<dyn Error>> {
+    Ok(persist::load_from_path(path)?)
+}
+
+fn write_vocab(path: &str, vocab: &Vocab) -> Result<(), Box<dyn Error>> {
+    Ok(persist::save_to_path(vocab, path)?)
+}
+
+fn build(path: &str, inputs: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut vocab = Vocab::new();
+    let mut add = |reader: &mut dyn BufRead| -> io::Result<()> {
+        for line in reader.lines() {
+            for token in stream::tokens(&line?) {
+                vocab.integerise(token.to_string());
+            }
+        }
+        Ok(())
+    };
+    if inputs.is_empty() {
+        let stdin = io::stdin();
+        add(&mut stdin.lock())?;
+    }
+    for input in inputs {
+        add(&mut BufReader::new(File::open(input)?))?;
+    }
+
+    write_vocab(path, &vocab)
+}
+
+fn encode(path: &str, update: bool) -> Result<(), Box<dyn Error>> {
+    let mut vocab = read_vocab(path)?;
+    let size = vocab.size();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let result = stream::encode(stdin.lock(), &mut out, &mut vocab, update);
+    out.flush()?;
+    // tokens added before an error are kept, as their ids have already been written
+    if vocab.size() > size {
+        write_vocab(path, &vocab)?;
+    }
+    result?;
+    Ok(())
+}
+
+/// Replaces every whitespace-separated field of stdin using `f` and keeps the line structure.
+fn map_fields<F>(mut f: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&str) -> Result<String, Box<dyn Error>>,
+{
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let fields = stream::tokens(&line)
+            .map(&mut f)
+            .collect::<Result<Vec<_>, _>>()?;
+        writeln!(out, "{}", fields.join(" "))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn apply(vocab: &Vocab) -> Result<(), Box<dyn Error>> {
+    map_fields(|token| match vocab.find_key_borrowed(token) {
+        Some(k) => Ok(k.to_string()),
+        None => Err(format!("unknown token {:?}", token).into()),
+    })
+}
+
+fn invert(vocab: &Vocab) -> Result<(), Box<dyn Error>> {
+    map_fields(|field| {
+        let k: usize = field.parse().map_err(|_| format!("malformed id {:?}", field))?;
+        match vocab.find_value(k) {
+            Some(token) => Ok(token.clone()),
+            None => Err(format!("unknown id {}", k).into()),
+        }
+    })
+}
+
+fn info(vocab: &Vocab) {
+    let bytes: usize = vocab.values().iter().map(String::len).sum();
+    let longest = vocab.values().iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    println!("values:  {}", vocab.size());
+    println!("bytes:   {}", bytes);
+    println!("longest: {} characters", longest);
+}
+
+fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match (args.first().map(String::as_str), args.get(1)) {
+        (Some("build"), Some(path)) => build(path, &args[2..]),
+        (Some("apply"), Some(path)) if args.len() == 2 => apply(&read_vocab(path)?),
+        (Some("encode"), Some(path)) if args.len() == 2 => encode(path, false),
+        (Some("encode"), Some(path)) if args.len() == 3 && args[2] == "--update" => encode(path, true),
+        (Some("invert"), Some(path)) if args.len() == 2 => invert(&read_vocab(path)?),
+        (Some("info"), Some(path)) if args.len() == 2 => {
+            info(&read_vocab(path)?);
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,244 @@
+//! Discretising continuous values into bins whose labels are integerised.
+
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+
+#[cfg(feature = "serialisation")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use {HashIntegeriser, Integeriser};
+
+/// Error for bins that cannot be constructed.  Contains the reason.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InvalidBins(pub &'static str);
+
+impl fmt::Display for InvalidBins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid bins: {}", self.0)
+    }
+}
+
+impl Error for InvalidBins {}
+
+/// Partition of the real numbers into intervals at finite, strictly
+/// increasing edges `e_0 < … < e_{n-1}`.
+/// Bin `0` is `(-inf, e_0)`, bin `i` is `[e_{i-1}, e_i)` and bin `n` is `[e_{n-1}, inf)`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::binned::Bins;
+///
+/// let bins = Bins::from_edges(vec![0.0, 10.0]).unwrap();
+///
+/// assert_eq!(bins.len(), 3);
+/// assert_eq!(bins.bin(-1.5), Some(0));
+/// assert_eq!(bins.bin(0.0), Some(1));
+/// assert_eq!(bins.bin(f64::INFINITY), Some(2));
+/// assert_eq!(bins.bin(f64::NAN), None);
+/// assert_eq!(bins.label(1), "[0, 10)");
+///
+/// assert_eq!(Bins::equal_width(0.0, 1.0, 4).unwrap().edges(), &[0.25, 0.5, 0.75]);
+/// assert_eq!(Bins::quantiles(&[5.0, 1.0, 3.0, 2.0, 4.0, 6.0], 3).unwrap().edges(), &[3.0, 5.0]);
+/// assert!(Bins::from_edges(vec![1.0, 1.0]).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bins {
+    edges: Vec<f64>,
+}
+
+impl Bins {
+    /// Bins with the given edges.
+    /// Fails if the edges are not finite and strictly increasing.
+    pub fn from_edges(edges: Vec<f64>) -> Result<Self, InvalidBins> {
+        if edges.iter().any(|e| !e.is_finite()) {
+            return Err(InvalidBins("the edges must be finite"));
+        }
+        if edges.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(InvalidBins("the edges must be strictly increasing"));
+        }
+        Ok(Bins { edges })
+    }
+
+    /// `n` bins that divide `[min, max)` into intervals of equal width,
+    /// where the first and the last bin extend to infinity.
+    /// Fails if `n` is `0` or `[min, max)` is empty or not finite.
+    pub fn equal_width(min: f64, max: f64, n: usize) -> Result<Self, InvalidBins> {
+        if n == 0 {
+            return Err(InvalidBins("there must be at least one bin"));
+        }
+        if min.partial_cmp(&max) != Some(Ordering::Less) {
+            return Err(InvalidBins("the minimum must be smaller than the maximum"));
+        }
+        let width = (max - min) / n as f64;
+        Bins::from_edges((1..n).map(|i| min + width * i as f64).collect())
+    }
+
+    /// At most `n` bins that contain roughly equally many of the `samples`,
+    /// ignoring NaNs.  Repeated samples may merge bins.
+    /// Fails if `n` is `0` or there is no sample.
+    pub fn quantiles(samples: &[f64], n: usize) -> Result<Self, InvalidBins> {
+        if n == 0 {
+            return Err(InvalidBins("there must be at least one bin"));
+        }
+        let mut sorted: Vec<f64> = samples.iter().cloned().filter(|x| !x.is_nan()).collect();
+        if sorted.is_empty() {
+            return Err(InvalidBins("quantiles require at least one sample"));
+        }
+        sorted.sort_by(|x, y| x.partial_cmp(y).expect("NaNs are removed"));
+
+        let mut edges: Vec<f64> = (1..n)
+            .map(|i| sorted[i * sorted.len() / n])
+            .filter(|e| e.is_finite())
+            .collect();
+        edges.dedup();
+        Bins::from_edges(edges)
+    }
+
+    /// The edges between the bins.
+    pub fn edges(&self) -> &[f64] {
+        &self.edges
+    }
+
+    /// Number of bins, i.e. one more than the number of edges.
+    pub fn len(&self) -> usize {
+        self.edges.len() + 1
+    }
+
+    /// Always `false`, since there is at least one bin.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The bin that contains `x`, or `None` if `x` is NaN.
+    pub fn bin(&self, x: f64) -> Option<usize> {
+        if x.is_nan() {
+            None
+        } else {
+            Some(self.edges.partition_point(|&e| e <= x))
+        }
+    }
+
+    /// Human-readable label of the bin `b`, e.g. `[0.5, 1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no bin `b`.
+    pub fn label(&self, b: usize) -> String {
+        assert!(b < self.len(), "there is no bin {} among {} bins", b, self.len());
+        match (b.checked_sub(1).map(|i| self.edges[i]), self.edges.get(b)) {
+            (None, None) => "(-inf, inf)".to_string(),
+            (None, Some(hi)) => format!("(-inf, {})", hi),
+            (Some(lo), None) => format!("[{}, inf)", lo),
+            (Some(lo), Some(hi)) => format!("[{}, {})", lo, hi),
+        }
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl Serialize for Bins {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.edges.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<'de> Deserialize<'de> for Bins {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let edges = Vec::deserialize(deserializer)?;
+        Bins::from_edges(edges).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Integeriser for continuous values that assigns to every value the
+/// integer of the label of its bin.  The labels are integerised by an
+/// inner integeriser of type `I` in the order in which their bins first
+/// occur.
+///
+/// Serialization writes the edges of the bins together with the inner integeriser.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::binned::{BinnedIntegeriser, Bins};
+///
+/// let ages = [34.0, 71.0, 18.0, 45.0, 29.0, 52.0];
+/// let mut integeriser = BinnedIntegeriser::new(Bins::quantiles(&ages, 3).unwrap());
+///
+/// let k = integeriser.integerise(40.0_f32).unwrap();
+/// assert_eq!(k, 0);
+/// assert_eq!(integeriser.integerise(12.5).unwrap(), 1);
+/// assert_eq!(integeriser.integerise(f64::NAN), None);
+///
+/// assert_eq!(integeriser.find_key(44.0), Some(k));
+/// assert_eq!(integeriser.find_label(k), Some("[34, 52)"));
+/// assert_eq!(integeriser.find_key(90.0), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BinnedIntegeriser<I = HashIntegeriser<String>> {
+    bins: Bins,
+    labels: I,
+}
+
+impl BinnedIntegeriser {
+    /// Constructs an integeriser for the given bins and no labels.
+    pub fn new(bins: Bins) -> Self {
+        BinnedIntegeriser::with_labels(bins, HashIntegeriser::new())
+    }
+}
+
+impl<I: Integeriser<Item = String>> BinnedIntegeriser<I> {
+    /// Constructs an integeriser for the given bins that integerises their
+    /// labels with `labels`.
+    pub fn with_labels(bins: Bins, labels: I) -> Self {
+        BinnedIntegeriser { bins, labels }
+    }
+
+    /// Returns the integer of the bin of `x`, or `None` if `x` is NaN.
+    pub fn integerise<F: Into<f64>>(&mut self, x: F) -> Option<usize> {
+        let b = self.bins.bin(x.into())?;
+        Some(self.labels.integerise(self.bins.label(b)))
+    }
+
+    /// Lookup the integer of the bin of `x`, if its label is integerised.
+    pub fn find_key<F: Into<f64>>(&self, x: F) -> Option<usize> {
+        let b = self.bins.bin(x.into())?;
+        self.labels.find_key(&self.bins.label(b))
+    }
+
+    /// Lookup the label of the bin that corresponds to the integer `k`.
+    pub fn find_label(&self, k: usize) -> Option<&str> {
+        self.labels.find_value(k).map(String::as_str)
+    }
+
+    /// Number of bins whose labels are integerised.
+    pub fn size(&self) -> usize {
+        self.labels.size()
+    }
+
+    /// The bins.
+    pub fn bins(&self) -> &Bins {
+        &self.bins
+    }
+
+    /// The integeriser of the labels.
+    pub fn labels(&self) -> &I {
+        &self.labels
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<I: Serialize> Serialize for BinnedIntegeriser<I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.bins, &self.labels).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<'de, I: Deserialize<'de>> Deserialize<'de> for BinnedIntegeriser<I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (bins, labels) = Deserialize::deserialize(deserializer)?;
+        Ok(BinnedIntegeriser { bins, labels })
+    }
+}
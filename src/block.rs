@@ -0,0 +1,235 @@
+//! Integerising values in blocks independently of the integeriser, e.g. by
+//! worker threads, and adding the blocks in bulk.
+//!
+//! The ids that a block assigns are provisional: they are only final if the
+//! blocks are filled completely and committed in the order in which they
+//! were taken, and no other values are integerised in the meantime.
+//! Otherwise `HashIntegeriser::commit_block` renumbers the values of the
+//! block, so that the ids of the integeriser stay consecutive, and returns
+//! the renumbering, which has to be applied to all provisional ids.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::ops::Range;
+use std::sync::{Arc, Weak};
+
+use storage::Storage;
+use {HashIntegeriser, Integeriser};
+
+/// Error for provisional ids beyond `usize::MAX`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IdOverflow;
+
+impl fmt::Display for IdOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the ids would exceed {}", usize::MAX)
+    }
+}
+
+impl Error for IdOverflow {}
+
+/// Provisional ids handed out by a `HashIntegeriser` to blocks that have not
+/// been committed or dropped yet.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Blocks {
+    /// End of the provisional ids of the latest block.
+    end: usize,
+    /// Shared by all live blocks, so that the provisional ids start over at
+    /// the size of the integeriser once there are none.
+    live: Weak<()>,
+}
+
+/// Block of consecutive provisional ids taken from a `HashIntegeriser`, see
+/// `HashIntegeriser::provisional_block`.
+#[derive(Clone, Debug)]
+pub struct ProvisionalBlock<A: Eq + Hash> {
+    ids: Range<usize>,
+    values: Vec<(usize, A)>,
+    rmap: HashMap<A, usize>,
+    _live: Arc<()>,
+}
+
+impl<A: Clone + Eq + Hash> ProvisionalBlock<A> {
+    /// Returns the provisional id of `a` in this block, assigning the next
+    /// unused id of the block if necessary.
+    /// Returns `None` if `a` is new and all ids of the block are used.
+    pub fn assign(&mut self, a: A) -> Option<usize> {
+        if let Some(&k) = self.rmap.get(&a) {
+            return Some(k);
+        }
+        let k = self.ids.next()?;
+        self.values.push((k, a.clone()));
+        self.rmap.insert(a, k);
+        Some(k)
+    }
+
+    /// Number of ids of the block that are not used yet.
+    pub fn remaining(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<A: Clone + Eq + Hash, S: BuildHasher, V: Storage<A>> HashIntegeriser<A, S, V> {
+    /// Takes a block of `n` provisional ids for values that are integerised
+    /// independently of `self`, e.g. by a worker thread, and later added
+    /// with `commit_block`.
+    /// The ids follow those of all values and of all blocks that are still
+    /// live, and they are provisional, see the module documentation.
+    /// Fails if the ids would exceed `usize::MAX`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use std::thread;
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let shared = Arc::new(Mutex::new(HashIntegeriser::new()));
+    /// let workers: Vec<_> = vec!["a b c", "c d"]
+    ///     .into_iter()
+    ///     .map(|text| {
+    ///         let shared = shared.clone();
+    ///         thread::spawn(move || {
+    ///             let mut block = shared.lock().unwrap().provisional_block(16).unwrap();
+    ///             let ids: Vec<usize> = text.split(' ').map(|w| block.assign(w).unwrap()).collect();
+    ///             let remap = shared.lock().unwrap().commit_block(block);
+    ///             ids.into_iter().map(|k| remap.iter().find(|r| r.0 == k).map_or(k, |r| r.1)).collect::<Vec<_>>()
+    ///         })
+    ///     })
+    ///     .collect();
+    ///
+    /// let ids: Vec<Vec<usize>> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+    /// let integeriser = shared.lock().unwrap();
+    /// assert_eq!(integeriser.size(), 4);
+    /// assert!(ids.iter().flatten().all(|&k| k < 4));
+    /// assert_eq!(ids[0][2], ids[1][0]);
+    /// assert_eq!(integeriser.find_value(ids[1][1]), Some(&"d"));
+    /// ```
+    pub fn provisional_block(&mut self, n: usize) -> Result<ProvisionalBlock<A>, IdOverflow> {
+        let live = match self.blocks.live.upgrade() {
+            Some(live) => live,
+            None => {
+                let live = Arc::new(());
+                self.blocks = Blocks { end: 0, live: Arc::downgrade(&live) };
+                live
+            }
+        };
+        let start = self.blocks.end.max(self.map.len());
+        let end = start.checked_add(n).ok_or(IdOverflow)?;
+        self.blocks.end = end;
+        Ok(ProvisionalBlock { ids: start..end, values: Vec::new(), rmap: HashMap::new(), _live: live })
+    }
+
+    /// Adds the values of a block taken with `provisional_block`, in the
+    /// order of their provisional ids, so that the ids stay consecutive.
+    /// Returns the pairs of the provisional id and the final id of the
+    /// values whose ids differ, i.e. of values that `self` already knows and
+    /// of values that follow ids which are not used by the time the block is
+    /// committed.  The pairs are empty if the blocks are filled completely
+    /// and committed in the order in which they were taken, and no other
+    /// values are integerised in the meantime.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// let mut first = integeriser.provisional_block(2).unwrap();
+    /// let mut second = integeriser.provisional_block(2).unwrap();
+    ///
+    /// assert_eq!(second.assign("c"), Some(2));
+    /// assert_eq!(first.assign("a"), Some(0));
+    /// assert_eq!(first.assign("b"), Some(1));
+    ///
+    /// assert_eq!(integeriser.commit_block(first), vec![]);
+    /// assert_eq!(integeriser.commit_block(second), vec![]);
+    ///
+    /// let mut third = integeriser.provisional_block(8).unwrap();
+    /// assert_eq!(third.assign("b"), Some(3));
+    /// assert_eq!(third.assign("d"), Some(4));
+    /// assert_eq!(integeriser.commit_block(third), vec![(3, 1), (4, 3)]);
+    /// assert_eq!(integeriser.size(), 4);
+    /// assert_eq!(integeriser.provisional_block(1).unwrap().assign("e"), Some(4));
+    /// ```
+    pub fn commit_block(&mut self, block: ProvisionalBlock<A>) -> Vec<(usize, usize)> {
+        trace_span!("commit_block", values = block.values.len());
+        let mut remap = Vec::new();
+        for (k, a) in block.values {
+            let j = self.integerise(a);
+            if j != k {
+                remap.push((k, j));
+            }
+        }
+        remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {HashIntegeriser, Integeriser};
+
+    #[test]
+    fn out_of_order_commits_are_renumbered() {
+        let mut integeriser = HashIntegeriser::new();
+        let mut first = integeriser.provisional_block(2).unwrap();
+        let mut second = integeriser.provisional_block(2).unwrap();
+        first.assign("a");
+        first.assign("b");
+        second.assign("c");
+        second.assign("d");
+
+        assert_eq!(integeriser.commit_block(second), vec![(2, 0), (3, 1)]);
+        assert_eq!(integeriser.commit_block(first), vec![(0, 2), (1, 3)]);
+        assert_eq!(integeriser.find_value(0), Some(&"c"));
+        assert_eq!(integeriser.find_value(3), Some(&"b"));
+    }
+
+    #[test]
+    fn dropped_blocks_leave_no_gap() {
+        let mut integeriser = HashIntegeriser::new();
+        let mut first = integeriser.provisional_block(4).unwrap();
+        drop(integeriser.provisional_block(4).unwrap());
+        first.assign("a");
+        assert_eq!(integeriser.commit_block(first), vec![]);
+
+        let mut next = integeriser.provisional_block(4).unwrap();
+        assert_eq!(next.assign("b"), Some(1));
+        assert_eq!(integeriser.commit_block(next), vec![]);
+        assert_eq!(integeriser.size(), 2);
+    }
+
+    #[test]
+    fn live_blocks_keep_their_ids_apart() {
+        let mut integeriser = HashIntegeriser::new();
+        let first = integeriser.provisional_block(4).unwrap();
+        drop(integeriser.provisional_block(4).unwrap());
+        let mut third = integeriser.provisional_block(1).unwrap();
+        assert_eq!(third.assign("a"), Some(8));
+        drop(first);
+        assert_eq!(integeriser.commit_block(third), vec![(8, 0)]);
+    }
+
+    #[test]
+    fn interleaved_integerise_is_renumbered() {
+        let mut integeriser = HashIntegeriser::new();
+        let mut block = integeriser.provisional_block(2).unwrap();
+        assert_eq!(block.assign("a"), Some(0));
+        assert_eq!(block.assign("b"), Some(1));
+        assert_eq!(block.assign("c"), None);
+        assert_eq!(block.remaining(), 0);
+
+        assert_eq!(integeriser.integerise("b"), 0);
+        assert_eq!(integeriser.commit_block(block), vec![(0, 1), (1, 0)]);
+        assert_eq!(integeriser.size(), 2);
+    }
+
+    #[test]
+    fn overflowing_blocks_are_rejected() {
+        let mut integeriser: HashIntegeriser<&str> = HashIntegeriser::new();
+        let _block = integeriser.provisional_block(usize::MAX).unwrap();
+        assert!(integeriser.provisional_block(1).is_err());
+    }
+}
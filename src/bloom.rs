@@ -0,0 +1,387 @@
+//! Bloom filter that answers most lookups of unknown values without
+//! consulting the integeriser behind it.
+
+use std::collections::hash_map::DefaultHasher;
+use std::f64::consts::LN_2;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use canonical::Canonicalise;
+use Integeriser;
+
+/// Probabilistic set of values without false negatives.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BloomFilter<A: Hash> {
+    bits: Vec<u64>,
+    hashes: u32,
+    values: PhantomData<fn(&A)>,
+}
+
+impl<A: Hash> BloomFilter<A> {
+    /// Constructs an empty filter whose false-positive rate is about
+    /// `false_positive_rate` once it contains `expected_values` values.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `0 < false_positive_rate < 1`.
+    pub fn new(expected_values: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false-positive rate must be between 0 and 1"
+        );
+        let n = expected_values.max(1) as f64;
+        let bits = (-n * false_positive_rate.ln() / (LN_2 * LN_2)).ceil().max(64.0);
+        let hashes = (bits / n * LN_2).round().max(1.0) as u32;
+
+        BloomFilter {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes,
+            values: PhantomData,
+        }
+    }
+
+    /// Position of the `i`-th bit that corresponds to a value with hash `h`.
+    fn position(&self, h: u64, i: u32) -> (usize, u64) {
+        let step = h.rotate_left(32) | 1;
+        let p = h.wrapping_add(u64::from(i).wrapping_mul(step)) % (self.bits.len() as u64 * 64);
+        ((p / 64) as usize, 1 << (p % 64))
+    }
+
+    /// Adds `a` to the filter.
+    pub fn insert(&mut self, a: &A) {
+        self.insert_hash(hash(a))
+    }
+
+    fn insert_hash(&mut self, h: u64) {
+        for i in 0..self.hashes {
+            let (word, mask) = self.position(h, i);
+            self.bits[word] |= mask;
+        }
+    }
+
+    /// Returns `false` if `a` has certainly not been added to the filter.
+    pub fn may_contain(&self, a: &A) -> bool {
+        self.may_contain_hash(hash(a))
+    }
+
+    fn may_contain_hash(&self, h: u64) -> bool {
+        (0..self.hashes).all(|i| {
+            let (word, mask) = self.position(h, i);
+            self.bits[word] & mask != 0
+        })
+    }
+}
+
+fn hash<A: Hash>(a: &A) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    a.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Form in which `BloomFiltered` adds values to its filter and looks them
+/// up.  Values that the inner integeriser considers equal must have equal
+/// forms, e.g. their canonical keys if the inner integeriser canonicalises
+/// values, see `Canonical`.
+pub trait FilterKey<A> {
+    /// Feeds the form of `a` into `state`.
+    fn hash_key<H: Hasher>(&self, a: &A, state: &mut H);
+}
+
+/// The values themselves, which `BloomFiltered` uses by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Exact;
+
+impl<A: Hash> FilterKey<A> for Exact {
+    fn hash_key<H: Hasher>(&self, a: &A, state: &mut H) {
+        a.hash(state)
+    }
+}
+
+/// The canonical keys of the values w.r.t. a `Canonicalise` implementation,
+/// for inner integerisers that canonicalise values with it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Canonical<C>(pub C);
+
+impl<A, C: Canonicalise<A>> FilterKey<A> for Canonical<C> {
+    fn hash_key<H: Hasher>(&self, a: &A, state: &mut H) {
+        self.0.canonicalise(a).hash(state)
+    }
+}
+
+/// Integeriser that keeps a `BloomFilter` over the values of an inner
+/// integeriser, so that `find_key` for most unknown values returns without
+/// accessing the inner integeriser.  This pays off for inner integerisers
+/// with expensive lookups.
+/// The filter contains the values in the form `K`, see `FilterKey`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::bloom::{BloomFiltered, Canonical};
+/// use integeriser::canonical::CanonicalIntegeriser;
+///
+/// let mut integeriser = BloomFiltered::new(HashIntegeriser::new(), 1000, 0.01);
+/// let k = integeriser.integerise("known");
+///
+/// assert_eq!(integeriser.find_key(&"known"), Some(k));
+/// assert_eq!(integeriser.find_key(&"unknown"), None);
+///
+/// fn lower(w: &&str) -> String {
+///     w.to_lowercase()
+/// }
+///
+/// let mut integeriser = BloomFiltered::with_key(CanonicalIntegeriser::new(lower), Canonical(lower), 1000, 0.01);
+/// let k = integeriser.integerise("Rose");
+///
+/// assert_eq!(integeriser.find_key(&"ROSE"), Some(k));
+/// ```
+#[derive(Clone, Debug)]
+pub struct BloomFiltered<I: Integeriser, K = Exact>
+where
+    I::Item: Hash,
+{
+    inner: I,
+    filter: BloomFilter<I::Item>,
+    key: K,
+}
+
+impl<I: Integeriser> BloomFiltered<I>
+where
+    I::Item: Hash,
+{
+    /// Wraps `inner`, which compares values by `Eq`, and adds all of its
+    /// values to a new filter, see `BloomFilter::new`.
+    pub fn new(inner: I, expected_values: usize, false_positive_rate: f64) -> Self {
+        BloomFiltered::with_key(inner, Exact, expected_values, false_positive_rate)
+    }
+}
+
+impl<I: Integeriser, K: FilterKey<I::Item>> BloomFiltered<I, K>
+where
+    I::Item: Hash,
+{
+    /// Wraps `inner` and adds the forms `key` of all of its values to a new
+    /// filter, see `BloomFilter::new`.
+    pub fn with_key(inner: I, key: K, expected_values: usize, false_positive_rate: f64) -> Self {
+        let mut integeriser = BloomFiltered {
+            inner,
+            filter: BloomFilter::new(expected_values, false_positive_rate),
+            key,
+        };
+        for k in 0..integeriser.inner.size() {
+            if let Some(a) = integeriser.inner.find_value(k) {
+                let h = integeriser.hash(a);
+                integeriser.filter.insert_hash(h);
+            }
+        }
+        integeriser
+    }
+
+    fn hash(&self, a: &I::Item) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash_key(a, &mut hasher);
+        hasher.finish()
+    }
+
+    /// The inner integeriser.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// The filter over the forms of the values of the inner integeriser.
+    /// Unless `K` is `Exact`, it has to be queried with `BloomFiltered::find_key`.
+    pub fn filter(&self) -> &BloomFilter<I::Item> {
+        &self.filter
+    }
+}
+
+impl<I: Integeriser, K: FilterKey<I::Item>> Integeriser for BloomFiltered<I, K>
+where
+    I::Item: Hash,
+{
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let h = self.hash(&a);
+        self.filter.insert_hash(h);
+        self.inner.integerise(a)
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        if self.filter.may_contain_hash(self.hash(a)) {
+            self.inner.find_key(a)
+        } else {
+            None
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
+
+/// Integeriser whose operations may fail, e.g. because it keeps its values
+/// on a server, such as `shared::RedisIntegeriser`.  `TryBloomFiltered`
+/// answers lookups of unknown values in front of it.
+pub trait TryIntegeriser {
+    type Item;
+    type Error;
+
+    /// Returns a unique integer for the given value `a`, see `Integeriser::integerise`.
+    fn try_integerise(&mut self, a: Self::Item) -> Result<usize, Self::Error>;
+
+    /// Lookup the integer that corresponds to the value `a`, see `Integeriser::find_key`.
+    fn try_find_key(&mut self, a: &Self::Item) -> Result<Option<usize>, Self::Error>;
+}
+
+/// `BloomFiltered` for a `TryIntegeriser`, so that `try_find_key` for most
+/// unknown values returns without contacting a server or a disk.
+/// The filter starts out empty and only learns the values that are
+/// integerised, found or inserted through the wrapper.  Values that the
+/// inner integeriser already knows, or that other processes add to it,
+/// have to be added with `insert`, otherwise their lookups miss.
+/// The filter contains the values in the form `K`, see `FilterKey`.
+/// For an example see `shared::RedisIntegeriser`.
+#[derive(Clone, Debug)]
+pub struct TryBloomFiltered<I: TryIntegeriser, K = Exact>
+where
+    I::Item: Hash,
+{
+    inner: I,
+    filter: BloomFilter<I::Item>,
+    key: K,
+}
+
+impl<I: TryIntegeriser> TryBloomFiltered<I>
+where
+    I::Item: Hash,
+{
+    /// Wraps `inner`, which compares values by `Eq`, with a new, empty
+    /// filter, see `BloomFilter::new`.
+    pub fn new(inner: I, expected_values: usize, false_positive_rate: f64) -> Self {
+        TryBloomFiltered::with_key(inner, Exact, expected_values, false_positive_rate)
+    }
+}
+
+impl<I: TryIntegeriser, K: FilterKey<I::Item>> TryBloomFiltered<I, K>
+where
+    I::Item: Hash,
+{
+    /// Wraps `inner` with a new, empty filter of the forms `key`, see `BloomFilter::new`.
+    pub fn with_key(inner: I, key: K, expected_values: usize, false_positive_rate: f64) -> Self {
+        TryBloomFiltered { inner, filter: BloomFilter::new(expected_values, false_positive_rate), key }
+    }
+
+    fn hash(&self, a: &I::Item) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.key.hash_key(a, &mut hasher);
+        hasher.finish()
+    }
+
+    /// Adds `a`, which the inner integeriser knows, to the filter.
+    pub fn insert(&mut self, a: &I::Item) {
+        let h = self.hash(a);
+        self.filter.insert_hash(h);
+    }
+
+    /// The inner integeriser.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// The mutable inner integeriser.  Values that are integerised through
+    /// it have to be added to the filter with `insert`.
+    pub fn inner_mut(&mut self) -> &mut I {
+        &mut self.inner
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: TryIntegeriser, K: FilterKey<I::Item>> TryIntegeriser for TryBloomFiltered<I, K>
+where
+    I::Item: Hash,
+{
+    type Item = I::Item;
+    type Error = I::Error;
+
+    fn try_integerise(&mut self, a: I::Item) -> Result<usize, I::Error> {
+        let h = self.hash(&a);
+        let k = self.inner.try_integerise(a)?;
+        self.filter.insert_hash(h);
+        Ok(k)
+    }
+
+    fn try_find_key(&mut self, a: &I::Item) -> Result<Option<usize>, I::Error> {
+        if self.filter.may_contain_hash(self.hash(a)) {
+            self.inner.try_find_key(a)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{TryBloomFiltered, TryIntegeriser};
+
+    /// Remote integeriser that counts how often it is asked.
+    #[derive(Default)]
+    struct Remote {
+        ids: HashMap<String, usize>,
+        requests: usize,
+    }
+
+    impl TryIntegeriser for Remote {
+        type Item = String;
+        type Error = ();
+
+        fn try_integerise(&mut self, a: String) -> Result<usize, ()> {
+            self.requests += 1;
+            let k = self.ids.len();
+            Ok(*self.ids.entry(a).or_insert(k))
+        }
+
+        fn try_find_key(&mut self, a: &String) -> Result<Option<usize>, ()> {
+            self.requests += 1;
+            Ok(self.ids.get(a).cloned())
+        }
+    }
+
+    #[test]
+    fn misses_are_answered_locally() {
+        let mut integeriser = TryBloomFiltered::new(Remote::default(), 100, 0.001);
+        let k = integeriser.try_integerise("known".to_string()).unwrap();
+        assert_eq!(integeriser.try_find_key(&"known".to_string()), Ok(Some(k)));
+        assert_eq!(integeriser.inner().requests, 2);
+
+        for i in 0..100 {
+            assert_eq!(integeriser.try_find_key(&i.to_string()), Ok(None));
+        }
+        assert!(integeriser.inner().requests <= 3);
+    }
+
+    #[test]
+    fn inserted_values_are_looked_up() {
+        let mut remote = Remote::default();
+        remote.try_integerise("elsewhere".to_string()).unwrap();
+        let mut integeriser = TryBloomFiltered::new(remote, 100, 0.01);
+        integeriser.insert(&"elsewhere".to_string());
+        assert_eq!(integeriser.try_find_key(&"elsewhere".to_string()), Ok(Some(0)));
+    }
+}
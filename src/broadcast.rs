@@ -0,0 +1,97 @@
+//! Notifying other threads about newly integerised values.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use Integeriser;
+
+/// Integeriser that sends every value that is new to an inner integeriser,
+/// together with its integer, to all subscribers.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::broadcast::Broadcasting;
+///
+/// let mut integeriser = Broadcasting::new(HashIntegeriser::new());
+/// let receiver = integeriser.subscribe();
+///
+/// let writer = thread::spawn(move || receiver.iter().collect::<Vec<_>>());
+///
+/// integeriser.integerise("a");
+/// integeriser.integerise("b");
+/// integeriser.integerise("a");
+/// drop(integeriser);
+///
+/// assert_eq!(writer.join().unwrap(), vec![(0, "a"), (1, "b")]);
+/// ```
+#[derive(Debug)]
+pub struct Broadcasting<I: Integeriser> {
+    inner: I,
+    subscribers: Vec<Sender<(usize, I::Item)>>,
+}
+
+impl<I: Integeriser> Broadcasting<I>
+where
+    I::Item: Clone,
+{
+    /// Wraps `inner`.  Values already stored in `inner` are not broadcast.
+    pub fn new(inner: I) -> Self {
+        Broadcasting {
+            inner,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Returns a receiver for all values that are new to the inner
+    /// integeriser from now on.  Receivers that are dropped are
+    /// unsubscribed automatically.
+    pub fn subscribe(&mut self) -> Receiver<(usize, I::Item)> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// The inner integeriser.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Unwraps the inner integeriser, which closes all receivers.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Integeriser> Integeriser for Broadcasting<I>
+where
+    I::Item: Clone,
+{
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let old_size = self.inner.size();
+        if self.subscribers.is_empty() {
+            return self.inner.integerise(a);
+        }
+
+        let k = self.inner.integerise(a.clone());
+        if self.inner.size() > old_size {
+            self.subscribers.retain(|subscriber| subscriber.send((k, a.clone())).is_ok());
+        }
+        k
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        self.inner.find_key(a)
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
@@ -0,0 +1,194 @@
+//! Bounding the estimated memory footprint of integerisers.
+
+use std::error::Error;
+use std::fmt;
+use std::mem;
+
+use Integeriser;
+
+/// Estimate of the number of bytes a value occupies, including the memory
+/// it owns on the heap.
+pub trait MemSize {
+    /// Estimated number of bytes occupied by `self`.
+    fn mem_size(&self) -> usize;
+}
+
+macro_rules! inline_mem_size {
+    ($($t:ty),*) => {
+        $(
+            impl MemSize for $t {
+                fn mem_size(&self) -> usize {
+                    mem::size_of::<$t>()
+                }
+            }
+        )*
+    };
+}
+
+inline_mem_size!(bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, ());
+
+impl MemSize for &str {
+    fn mem_size(&self) -> usize {
+        mem::size_of::<&str>() + self.len()
+    }
+}
+
+impl MemSize for String {
+    fn mem_size(&self) -> usize {
+        mem::size_of::<String>() + self.capacity()
+    }
+}
+
+impl<T: MemSize> MemSize for Vec<T> {
+    fn mem_size(&self) -> usize {
+        mem::size_of::<Vec<T>>()
+            + (self.capacity() - self.len()) * mem::size_of::<T>()
+            + self.iter().map(MemSize::mem_size).sum::<usize>()
+    }
+}
+
+impl<T: MemSize> MemSize for Box<T> {
+    fn mem_size(&self) -> usize {
+        mem::size_of::<Box<T>>() + (**self).mem_size()
+    }
+}
+
+impl<T: MemSize> MemSize for Option<T> {
+    fn mem_size(&self) -> usize {
+        match *self {
+            Some(ref a) => mem::size_of::<Option<T>>() - mem::size_of::<T>() + a.mem_size(),
+            None => mem::size_of::<Option<T>>(),
+        }
+    }
+}
+
+impl<T: MemSize, U: MemSize> MemSize for (T, U) {
+    fn mem_size(&self) -> usize {
+        mem::size_of::<(T, U)>() - mem::size_of::<T>() - mem::size_of::<U>() + self.0.mem_size() + self.1.mem_size()
+    }
+}
+
+/// Error for a value that was not integerised because it would have
+/// exceeded the memory budget.  Contains the rejected value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BudgetExceeded<A>(pub A);
+
+impl<A> fmt::Display for BudgetExceeded<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the memory budget of the integeriser is exhausted")
+    }
+}
+
+impl<A: fmt::Debug> Error for BudgetExceeded<A> {}
+
+/// Integeriser that rejects new values once the estimated footprint of the
+/// values in an inner integeriser would exceed a budget.
+/// Every value is assumed to be stored twice (once in each direction of the
+/// mapping) together with its integer, as in `HashIntegeriser` and
+/// `BTreeIntegeriser`.
+///
+/// Use `try_integerise` to handle an exhausted budget;
+/// `Integeriser::integerise` panics instead.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::budget::Budgeted;
+///
+/// let mut integeriser = Budgeted::new(HashIntegeriser::new(), 200);
+///
+/// assert_eq!(integeriser.try_integerise("short".to_string()), Ok(0));
+/// assert!(integeriser.try_integerise("x".repeat(100)).is_err());
+/// assert_eq!(integeriser.try_integerise("short".to_string()), Ok(0));
+/// assert!(integeriser.bytes() <= 200);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Budgeted<I> {
+    inner: I,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl<I: Integeriser> Budgeted<I>
+where
+    I::Item: MemSize,
+{
+    /// Wraps `inner`, allowing its values to occupy an estimated `max_bytes` bytes.
+    pub fn new(inner: I, max_bytes: usize) -> Self {
+        let bytes = (0..inner.size())
+            .filter_map(|k| inner.find_value(k))
+            .map(footprint)
+            .sum();
+        Budgeted { inner, bytes, max_bytes }
+    }
+
+    /// Estimated number of bytes occupied by the values.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Number of bytes the values may occupy.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Returns a unique integer for the given value `a`, see
+    /// `Integeriser::integerise`, or `a` itself if `a` is new and does not
+    /// fit into the budget.
+    pub fn try_integerise(&mut self, a: I::Item) -> Result<usize, BudgetExceeded<I::Item>> {
+        if let Some(k) = self.inner.find_key(&a) {
+            return Ok(k);
+        }
+        let bytes = self.bytes + footprint(&a);
+        if bytes > self.max_bytes {
+            count!("integeriser_rejections_total", 1);
+            return Err(BudgetExceeded(a));
+        }
+        self.bytes = bytes;
+        Ok(self.inner.integerise(a))
+    }
+
+    /// The inner integeriser.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+pub(crate) fn footprint<A: MemSize>(a: &A) -> usize {
+    2 * a.mem_size() + mem::size_of::<usize>()
+}
+
+impl<I: Integeriser> Integeriser for Budgeted<I>
+where
+    I::Item: MemSize,
+{
+    type Item = I::Item;
+
+    /// # Panics
+    ///
+    /// Panics if `a` is new and does not fit into the budget.
+    fn integerise(&mut self, a: I::Item) -> usize {
+        match self.try_integerise(a) {
+            Ok(k) => k,
+            Err(_) => panic!("memory budget of {} bytes exhausted", self.max_bytes),
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        self.inner.find_key(a)
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
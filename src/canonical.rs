@@ -0,0 +1,109 @@
+//! Integerisation modulo a domain-specific equivalence of values.
+
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::hash::Hash;
+
+use Integeriser;
+
+/// Maps every value to a canonical key, such that two values are
+/// considered equivalent iff their keys are equal.
+pub trait Canonicalise<A> {
+    type Key: Eq + Hash;
+
+    /// The canonical key of the value `a`.
+    fn canonicalise(&self, a: &A) -> Self::Key;
+}
+
+impl<A, K: Eq + Hash, F: Fn(&A) -> K> Canonicalise<A> for F {
+    type Key = K;
+
+    fn canonicalise(&self, a: &A) -> K {
+        self(a)
+    }
+}
+
+#[cfg(feature = "normalisation")]
+impl Canonicalise<String> for ::normalise::Normalisation {
+    type Key = String;
+
+    fn canonicalise(&self, a: &String) -> String {
+        self.normalise(a)
+    }
+}
+
+/// Structure that maps to every equivalence class (w.r.t. a `Canonicalise`
+/// implementation `C`) of elements of type `A` an integer of type `usize`.
+/// The first value that is integerised from each class is stored as its
+/// representative.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::Integeriser;
+/// use integeriser::canonical::CanonicalIntegeriser;
+///
+/// let mut integeriser = CanonicalIntegeriser::new(|w: &&str| w.to_lowercase());
+///
+/// let k = integeriser.integerise("Rose");
+///
+/// assert_eq!(integeriser.integerise("ROSE"), k);
+/// assert_eq!(integeriser.find_key(&"rose"), Some(k));
+/// assert_eq!(integeriser.find_value(k), Some(&"Rose"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CanonicalIntegeriser<A, C: Canonicalise<A>> {
+    map: Vec<A>,
+    rmap: HashMap<C::Key, usize>,
+    canonicaliser: C,
+}
+
+impl<A, C: Canonicalise<A>> CanonicalIntegeriser<A, C> {
+    /// Constructs a new, empty `CanonicalIntegeriser<A, C>`.
+    pub fn new(canonicaliser: C) -> Self {
+        CanonicalIntegeriser {
+            map: Vec::new(),
+            rmap: HashMap::new(),
+            canonicaliser,
+        }
+    }
+
+    /// `Vec` containing the representatives of all equivalence classes
+    /// that have been integerised.
+    pub fn values(&self) -> &Vec<A> {
+        &self.map
+    }
+
+    /// The `Canonicalise` implementation that decides equivalence.
+    pub fn canonicaliser(&self) -> &C {
+        &self.canonicaliser
+    }
+}
+
+impl<A, C: Canonicalise<A>> Integeriser for CanonicalIntegeriser<A, C> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        match self.rmap.entry(self.canonicaliser.canonicalise(&a)) {
+            hash_map::Entry::Occupied(e) => *e.get(),
+            hash_map::Entry::Vacant(e) => {
+                let old_size = self.map.len();
+                self.map.push(a);
+                e.insert(old_size);
+                old_size
+            }
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        self.map.get(k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(&self.canonicaliser.canonicalise(a)).cloned()
+    }
+
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+}
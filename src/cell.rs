@@ -0,0 +1,101 @@
+//! Integerisers that add values through a shared reference.
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+use storage::{ChunkedStorage, Storage};
+use {HashIntegeriser, Integeriser};
+
+/// Single-threaded integeriser whose `integerise` takes `&self`, so that it
+/// can be shared by many parts of a program, e.g. by the functions of a
+/// recursive-descent parser, without passing `&mut` references around.
+/// Values never move, so the references returned by `find_value` remain
+/// valid while values are added.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::cell::CellIntegeriser;
+///
+/// fn parse<'a>(symbols: &'a CellIntegeriser<String>, input: &str) -> Vec<&'a String> {
+///     input
+///         .split(' ')
+///         .map(|w| symbols.find_value(symbols.integerise(w.to_string())).unwrap())
+///         .collect()
+/// }
+///
+/// let symbols = CellIntegeriser::new();
+/// let first = parse(&symbols, "S NP VP");
+/// let second = parse(&symbols, "NP D N");
+///
+/// assert_eq!(first[1], second[0]);
+/// assert_eq!(symbols.size(), 5);
+/// ```
+#[derive(Debug)]
+pub struct CellIntegeriser<A: Eq + Hash, S: BuildHasher = RandomState> {
+    inner: RefCell<HashIntegeriser<A, S, ChunkedStorage<A>>>,
+}
+
+impl<A: Eq + Hash> CellIntegeriser<A> {
+    /// Constructs a new, empty `CellIntegeriser<A>`.
+    pub fn new() -> Self {
+        CellIntegeriser::default()
+    }
+}
+
+impl<A: Eq + Hash, S: BuildHasher + Default> Default for CellIntegeriser<A, S> {
+    fn default() -> Self {
+        CellIntegeriser { inner: RefCell::new(HashIntegeriser::default()) }
+    }
+}
+
+impl<A: Clone + Eq + Hash, S: BuildHasher> CellIntegeriser<A, S> {
+    /// Returns a unique integer for the given value `a`, see `Integeriser::integerise`.
+    pub fn integerise(&self, a: A) -> usize {
+        self.inner.borrow_mut().integerise(a)
+    }
+
+    /// Lookup the value that corresponds to the integer `k`.
+    pub fn find_value(&self, k: usize) -> Option<&A> {
+        let inner = self.inner.borrow();
+        // values are only added through `&self`, and adding values to a
+        // `ChunkedStorage` never moves the stored values
+        inner.values().get(k).map(|a| unsafe { &*(a as *const A) })
+    }
+
+    /// Lookup the integer that corresponds to the value `a`.
+    pub fn find_key(&self, a: &A) -> Option<usize> {
+        self.inner.borrow().find_key(a)
+    }
+
+    /// Number of distinct values that are stored in the integeriser.
+    pub fn size(&self) -> usize {
+        self.inner.borrow().size()
+    }
+
+    /// Unwraps the underlying integeriser.
+    pub fn into_inner(self) -> HashIntegeriser<A, S, ChunkedStorage<A>> {
+        self.inner.into_inner()
+    }
+}
+
+impl<A: Clone + Eq + Hash, S: BuildHasher> Integeriser for CellIntegeriser<A, S> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        self.inner.get_mut().integerise(a)
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        CellIntegeriser::find_value(self, k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        CellIntegeriser::find_key(self, a)
+    }
+
+    fn size(&self) -> usize {
+        CellIntegeriser::size(self)
+    }
+}
@@ -0,0 +1,135 @@
+//! Counting how often the integers of a stream occur close to each other,
+//! e.g. for computing word embeddings in the manner of GloVe.
+
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(feature = "ndarray")]
+use ndarray::Array2;
+#[cfg(feature = "sprs")]
+use sprs::{CsMat, TriMat};
+
+/// Weight of a co-occurrence at distance `d`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Weighting {
+    /// Every co-occurrence has the weight `1`.
+    Uniform,
+    /// A co-occurrence at distance `d` has the weight `1 / d`, as in GloVe.
+    Harmonic,
+}
+
+impl Weighting {
+    fn weight(self, d: usize) -> f64 {
+        match self {
+            Weighting::Uniform => 1.0,
+            Weighting::Harmonic => 1.0 / d as f64,
+        }
+    }
+}
+
+/// Sparse, symmetric matrix of the weighted number of times two integers
+/// occur within a fixed distance of each other.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::cooccurrence::{Cooccurrence, Weighting};
+///
+/// let mut matrix = Cooccurrence::new(2, Weighting::Harmonic);
+/// matrix.count(vec![0, 1, 2]);
+///
+/// assert_eq!(matrix.get(0, 1), 1.0);
+/// assert_eq!(matrix.get(2, 0), 0.5);
+/// assert_eq!(matrix.get(0, 0), 0.0);
+/// assert_eq!(matrix.triplets()[..2], [(0, 1, 1.0), (0, 2, 0.5)]);
+///
+/// matrix.count(vec![3, 3]);
+/// assert_eq!(matrix.get(3, 3), 1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Cooccurrence {
+    window: usize,
+    weighting: Weighting,
+    counts: HashMap<(usize, usize), f64>,
+}
+
+impl Cooccurrence {
+    /// Constructs an empty matrix that counts co-occurrences at distances
+    /// of at most `window`.
+    pub fn new(window: usize, weighting: Weighting) -> Self {
+        Cooccurrence { window, weighting, counts: HashMap::new() }
+    }
+
+    /// Counts all co-occurrences in `ids`.  Co-occurrences do not span several calls.
+    pub fn count<T: IntoIterator<Item = usize>>(&mut self, ids: T) {
+        let mut recent = VecDeque::with_capacity(self.window);
+        for k in ids {
+            for (d, &j) in recent.iter().rev().enumerate() {
+                let weight = self.weighting.weight(d + 1);
+                *self.counts.entry((j, k)).or_insert(0.0) += weight;
+                // a value next to a copy of itself only has a single cell
+                if j != k {
+                    *self.counts.entry((k, j)).or_insert(0.0) += weight;
+                }
+            }
+            if self.window > 0 {
+                if recent.len() == self.window {
+                    recent.pop_front();
+                }
+                recent.push_back(k);
+            }
+        }
+    }
+
+    /// The weighted number of co-occurrences of `j` and `k`.
+    pub fn get(&self, j: usize, k: usize) -> f64 {
+        self.counts.get(&(j, k)).cloned().unwrap_or(0.0)
+    }
+
+    /// Adds the co-occurrences of `other` to `self`.
+    pub fn merge(&mut self, other: Cooccurrence) {
+        for (pair, count) in other.counts {
+            *self.counts.entry(pair).or_insert(0.0) += count;
+        }
+    }
+
+    /// Number of non-zero entries of the matrix.
+    pub fn nnz(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// All non-zero entries as triplets of row, column, and value, in
+    /// ascending order of rows and columns.
+    pub fn triplets(&self) -> Vec<(usize, usize, f64)> {
+        let mut triplets: Vec<_> = self.counts.iter().map(|(&(j, k), &c)| (j, k, c)).collect();
+        triplets.sort_unstable_by_key(|&(j, k, _)| (j, k));
+        triplets
+    }
+
+    /// The matrix as a `dim × dim` sparse matrix in CSR format.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an integer is not smaller than `dim`.
+    #[cfg(feature = "sprs")]
+    pub fn to_csr(&self, dim: usize) -> CsMat<f64> {
+        let mut matrix = TriMat::with_capacity((dim, dim), self.counts.len());
+        for (j, k, c) in self.triplets() {
+            matrix.add_triplet(j, k, c);
+        }
+        matrix.to_csr()
+    }
+
+    /// The matrix as a dense `dim × dim` array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an integer is not smaller than `dim`.
+    #[cfg(feature = "ndarray")]
+    pub fn to_dense(&self, dim: usize) -> Array2<f64> {
+        let mut matrix = Array2::zeros((dim, dim));
+        for (&(j, k), &c) in &self.counts {
+            matrix[[j, k]] = c;
+        }
+        matrix
+    }
+}
@@ -0,0 +1,93 @@
+//! Stable digests of the contents of integerisers.
+
+use blake3;
+
+use Integeriser;
+
+/// Digest of the values of an integeriser in the order of their integers.
+/// The digest only depends on the bytes of the values (not on hashers,
+/// platforms or crate versions), so it can be compared across machines.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser, BTreeIntegeriser};
+/// use integeriser::digest::ContentHash;
+///
+/// let mut a = HashIntegeriser::new();
+/// let mut b = BTreeIntegeriser::new();
+/// for w in &["to", "be", "or", "not"] {
+///     a.integerise(*w);
+///     b.integerise(*w);
+/// }
+///
+/// assert_eq!(a.content_hash(), b.content_hash());
+///
+/// b.integerise("that");
+/// assert_ne!(a.content_hash(), b.content_hash());
+/// ```
+pub trait ContentHash {
+    /// BLAKE3 digest over the number of values and, for every value, its
+    /// length and its bytes (all lengths as little-endian `u64`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if an integer below `size()` has no value, since the digest
+    /// would not tell the integers of the values apart.
+    fn content_hash(&self) -> [u8; 32];
+}
+
+impl<I> ContentHash for I
+where
+    I: Integeriser,
+    I::Item: AsRef<[u8]>,
+{
+    fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&(self.size() as u64).to_le_bytes());
+        for k in 0..self.size() {
+            let bytes = match self.find_value(k) {
+                Some(a) => a.as_ref(),
+                None => panic!("the integeriser has no value for the integer {}", k),
+            };
+            hasher.update(&(bytes.len() as u64).to_le_bytes());
+            hasher.update(bytes);
+        }
+        *hasher.finalize().as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ContentHash;
+    use Integeriser;
+
+    /// Integeriser that claims one more value than it has.
+    struct Gapped;
+
+    impl Integeriser for Gapped {
+        type Item = String;
+
+        fn integerise(&mut self, _: String) -> usize {
+            unimplemented!()
+        }
+
+        fn find_value(&self, _: usize) -> Option<&String> {
+            None
+        }
+
+        fn find_key(&self, _: &String) -> Option<usize> {
+            None
+        }
+
+        fn size(&self) -> usize {
+            1
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "no value for the integer 0")]
+    fn missing_values_are_not_skipped() {
+        Gapped.content_hash();
+    }
+}
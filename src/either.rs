@@ -0,0 +1,125 @@
+//! Choosing the implementation of an integeriser at runtime.
+
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use {BTreeIntegeriser, HashIntegeriser, Integeriser};
+
+/// Implementations that an `EitherIntegeriser` can be based on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// `HashIntegeriser`, written as `hash`.
+    Hash,
+    /// `BTreeIntegeriser`, written as `btree`.
+    BTree,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Backend::Hash => "hash",
+            Backend::BTree => "btree",
+        })
+    }
+}
+
+/// Error for parsing a string that names no `Backend`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownBackend(pub String);
+
+impl fmt::Display for UnknownBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown integeriser backend {:?}, expected \"hash\" or \"btree\"", self.0)
+    }
+}
+
+impl Error for UnknownBackend {}
+
+impl FromStr for Backend {
+    type Err = UnknownBackend;
+
+    fn from_str(s: &str) -> Result<Self, UnknownBackend> {
+        match s {
+            "hash" => Ok(Backend::Hash),
+            "btree" => Ok(Backend::BTree),
+            _ => Err(UnknownBackend(s.to_string())),
+        }
+    }
+}
+
+/// Integeriser whose implementation is chosen at runtime, e.g. from a
+/// configuration file, without making the surrounding code generic.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::Integeriser;
+/// use integeriser::either::{Backend, EitherIntegeriser};
+///
+/// let backend: Backend = "btree".parse().unwrap();
+/// let mut integeriser = EitherIntegeriser::new(backend);
+/// let k = integeriser.integerise("token");
+///
+/// assert_eq!(integeriser.backend(), Backend::BTree);
+/// assert_eq!(integeriser.find_value(k), Some(&"token"));
+/// assert!("trie".parse::<Backend>().is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EitherIntegeriser<A: Eq + Hash + Ord> {
+    /// Based on a `HashIntegeriser`.
+    Hash(HashIntegeriser<A>),
+    /// Based on a `BTreeIntegeriser`.
+    BTree(BTreeIntegeriser<A>),
+}
+
+impl<A: Eq + Hash + Ord> EitherIntegeriser<A> {
+    /// Constructs a new, empty integeriser with the given implementation.
+    pub fn new(backend: Backend) -> Self {
+        match backend {
+            Backend::Hash => EitherIntegeriser::Hash(HashIntegeriser::new()),
+            Backend::BTree => EitherIntegeriser::BTree(BTreeIntegeriser::new()),
+        }
+    }
+
+    /// The implementation that `self` is based on.
+    pub fn backend(&self) -> Backend {
+        match *self {
+            EitherIntegeriser::Hash(_) => Backend::Hash,
+            EitherIntegeriser::BTree(_) => Backend::BTree,
+        }
+    }
+}
+
+impl<A: Clone + Eq + Hash + Ord> Integeriser for EitherIntegeriser<A> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        match *self {
+            EitherIntegeriser::Hash(ref mut i) => i.integerise(a),
+            EitherIntegeriser::BTree(ref mut i) => i.integerise(a),
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        match *self {
+            EitherIntegeriser::Hash(ref i) => i.find_value(k),
+            EitherIntegeriser::BTree(ref i) => i.find_value(k),
+        }
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        match *self {
+            EitherIntegeriser::Hash(ref i) => i.find_key(a),
+            EitherIntegeriser::BTree(ref i) => i.find_key(a),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match *self {
+            EitherIntegeriser::Hash(ref i) => i.size(),
+            EitherIntegeriser::BTree(ref i) => i.size(),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Aligning pretrained word embeddings in text format (as distributed for
+//! GloVe and word2vec) to the ids of an integeriser.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+#[cfg(feature = "ndarray")]
+use std::ops::Range;
+
+#[cfg(feature = "ndarray")]
+use ndarray::{s, Array1, Array2, ArrayView1, ArrayView2, ArrayViewMut1, ArrayViewMut2, Axis};
+
+use Integeriser;
+#[cfg(feature = "ndarray")]
+use UnknownId;
+
+/// Correspondence between the rows of an embedding file and the ids of an
+/// integeriser, as computed by `align`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alignment {
+    /// For every row of the embedding file, the id of its word, or `None` if
+    /// the word is not in the integeriser or already occurred in an earlier row.
+    pub rows: Vec<Option<usize>>,
+    /// Ids (in ascending order) of the values for which the file has no row.
+    pub missing: Vec<usize>,
+}
+
+/// Reads an embedding file line by line and aligns it to `integeriser`.
+/// Every line consists of a word followed by its vector components.
+/// An optional word2vec header line (`<rows> <dimensions>`) is skipped and
+/// does not count as a row.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::embedding::align;
+///
+/// let mut vocab = HashIntegeriser::new();
+/// for w in &["the", "cat", "sat"] {
+///     vocab.integerise(w.to_string());
+/// }
+///
+/// let vectors = "3 2\nsat 0.1 0.2\ndog 0.3 0.4\nthe 0.5 0.6\n";
+/// let alignment = align(vectors.as_bytes(), &vocab).unwrap();
+///
+/// assert_eq!(alignment.rows, vec![Some(2), None, Some(0)]);
+/// assert_eq!(alignment.missing, vec![1]);
+/// ```
+pub fn align<R, I>(reader: R, integeriser: &I) -> io::Result<Alignment>
+where
+    R: BufRead,
+    I: Integeriser<Item = String>,
+{
+    let mut rows = Vec::new();
+    let mut covered = vec![false; integeriser.size()];
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let word = match line.split_whitespace().next() {
+            Some(word) => word,
+            None => continue,
+        };
+        if i == 0 && is_header(&line) {
+            continue;
+        }
+
+        let id = integeriser.find_key(&word.to_string()).filter(|&k| !covered[k]);
+        if let Some(k) = id {
+            covered[k] = true;
+        }
+        rows.push(id);
+    }
+
+    let missing = covered
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, c)| !c)
+        .map(|(k, _)| k)
+        .collect();
+
+    Ok(Alignment { rows, missing })
+}
+
+fn is_header(line: &str) -> bool {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    fields.len() == 2 && fields.iter().all(|f| f.parse::<usize>().is_ok())
+}
+
+/// Error for an embedding matrix with fewer rows than its integeriser has
+/// values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DimensionMismatch {
+    /// Number of rows of the matrix.
+    pub rows: usize,
+    /// Number of values of the integeriser.
+    pub size: usize,
+}
+
+impl fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the matrix has {} rows but the integeriser has {} values", self.rows, self.size)
+    }
+}
+
+impl Error for DimensionMismatch {}
+
+/// Integeriser paired with a matrix that holds one embedding per value,
+/// in the row of the value's integer.
+/// The matrix may have more rows than the integeriser has values; these
+/// rows belong to the values that are integerised next.
+/// Every value that is integerised for the first time and has no row yet
+/// gets a row of zeros.
+///
+/// # Example
+///
+/// ```
+/// extern crate ndarray;
+/// # extern crate integeriser;
+/// use ndarray::{array, Array2};
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::embedding::EmbeddingTable;
+///
+/// # fn main() {
+/// let mut vocab = HashIntegeriser::new();
+/// vocab.integerise("cat");
+/// vocab.integerise("dog");
+///
+/// let mut table = EmbeddingTable::new(vocab, array![[0.1, 0.2], [0.3, 0.4]]).unwrap();
+/// let k = table.integerise("fish");
+/// table.row_mut(k).unwrap().fill(1.0);
+///
+/// assert_eq!(table.rows_for(&[2, 0]), Ok(array![[1.0, 1.0], [0.1, 0.2]]));
+/// assert_eq!(table.rows(1..3), Ok(array![[0.3, 0.4], [1.0, 1.0]].view()));
+/// assert!(table.rows_for(&[3]).is_err());
+/// assert!(table.rows(2..4).is_err());
+/// assert_eq!(table.rows(7..7).unwrap().nrows(), 0);
+///
+/// let mut batch = ndarray::Array2::zeros((2, 2));
+/// table.rows_into(&[1, 1], batch.view_mut()).unwrap();
+/// assert_eq!(batch, array![[0.3, 0.4], [0.3, 0.4]]);
+///
+/// let mut reserved = EmbeddingTable::new(HashIntegeriser::new(), array![[0.5]]).unwrap();
+/// assert_eq!(reserved.integerise("cat"), 0);
+/// assert_eq!(reserved.row(0).unwrap()[0], 0.5);
+/// let mut vocab = HashIntegeriser::new();
+/// vocab.integerise("cat");
+/// assert!(EmbeddingTable::new(vocab, Array2::zeros((0, 1))).is_err());
+/// # }
+/// ```
+#[cfg(feature = "ndarray")]
+#[derive(Clone, Debug)]
+pub struct EmbeddingTable<I> {
+    integeriser: I,
+    matrix: Array2<f32>,
+}
+
+#[cfg(feature = "ndarray")]
+impl<I: Integeriser> EmbeddingTable<I> {
+    /// Pairs `integeriser` with `matrix`.
+    /// Fails if the matrix has fewer rows than `integeriser.size()`.
+    pub fn new(integeriser: I, matrix: Array2<f32>) -> Result<Self, DimensionMismatch> {
+        if matrix.nrows() < integeriser.size() {
+            return Err(DimensionMismatch { rows: matrix.nrows(), size: integeriser.size() });
+        }
+        Ok(EmbeddingTable { integeriser, matrix })
+    }
+
+    /// Pairs `integeriser` with embeddings of `dim` zeros each.
+    pub fn zeros(integeriser: I, dim: usize) -> Self {
+        let matrix = Array2::zeros((integeriser.size(), dim));
+        EmbeddingTable { integeriser, matrix }
+    }
+
+    /// Number of components of every embedding.
+    pub fn dim(&self) -> usize {
+        self.matrix.ncols()
+    }
+
+    /// The embedding of the value with the integer `k`.
+    pub fn row(&self, k: usize) -> Option<ArrayView1<'_, f32>> {
+        if k < self.matrix.nrows() {
+            Some(self.matrix.row(k))
+        } else {
+            None
+        }
+    }
+
+    /// The mutable embedding of the value with the integer `k`.
+    pub fn row_mut(&mut self, k: usize) -> Option<ArrayViewMut1<'_, f32>> {
+        if k < self.matrix.nrows() {
+            Some(self.matrix.row_mut(k))
+        } else {
+            None
+        }
+    }
+
+    /// The embeddings of the integers `ks`, one row each.
+    /// Fails with the first integer that corresponds to no value.
+    /// The rows are copied into a new matrix, see `rows` and `rows_into`
+    /// for alternatives that do not allocate.
+    pub fn rows_for(&self, ks: &[usize]) -> Result<Array2<f32>, UnknownId> {
+        self.check(ks)?;
+        Ok(self.matrix.select(Axis(0), ks))
+    }
+
+    /// The embeddings of the consecutive integers `ks`, without copying.
+    /// Fails with the last integer if it corresponds to no value.
+    pub fn rows(&self, ks: Range<usize>) -> Result<ArrayView2<'_, f32>, UnknownId> {
+        if ks.is_empty() {
+            return Ok(self.matrix.slice(s![0..0, ..]));
+        }
+        if ks.end > self.matrix.nrows() {
+            return Err(UnknownId(ks.end - 1));
+        }
+        Ok(self.matrix.slice(s![ks, ..]))
+    }
+
+    /// Copies the embeddings of the integers `ks` into the rows of `out`,
+    /// e.g. to reuse the same buffer for every batch.
+    /// Fails with the first integer that corresponds to no value, in which
+    /// case `out` is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out` does not have `ks.len()` rows of `self.dim()` components.
+    pub fn rows_into(&self, ks: &[usize], mut out: ArrayViewMut2<'_, f32>) -> Result<(), UnknownId> {
+        assert_eq!(out.dim(), (ks.len(), self.dim()), "the buffer has the wrong shape");
+        self.check(ks)?;
+        for (&k, mut row) in ks.iter().zip(out.rows_mut()) {
+            row.assign(&self.matrix.row(k));
+        }
+        Ok(())
+    }
+
+    fn check(&self, ks: &[usize]) -> Result<(), UnknownId> {
+        match ks.iter().find(|&&k| k >= self.matrix.nrows()) {
+            Some(&k) => Err(UnknownId(k)),
+            None => Ok(()),
+        }
+    }
+
+    /// The whole matrix, with one row per value and the reserved rows, see
+    /// `new`.
+    pub fn matrix(&self) -> ArrayView2<'_, f32> {
+        self.matrix.view()
+    }
+
+    /// The integeriser.
+    pub fn integeriser(&self) -> &I {
+        &self.integeriser
+    }
+
+    /// Unwraps the integeriser and the matrix.
+    pub fn into_parts(self) -> (I, Array2<f32>) {
+        (self.integeriser, self.matrix)
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<I: Integeriser> Integeriser for EmbeddingTable<I> {
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let k = self.integeriser.integerise(a);
+        // row `k` has to belong to the value with the integer `k`
+        if k >= self.matrix.nrows() {
+            let zeros = Array1::zeros(self.matrix.ncols());
+            while k >= self.matrix.nrows() {
+                // the row has as many components as the matrix has columns
+                let _ = self.matrix.push_row(zeros.view());
+            }
+        }
+        k
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.integeriser.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        self.integeriser.find_key(a)
+    }
+
+    fn size(&self) -> usize {
+        self.integeriser.size()
+    }
+}
@@ -0,0 +1,189 @@
+//! Integeriser that forgets values which have not been used for a while.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Structure that maps to every element of type `A` an integer of type `usize`,
+/// given that `A: Eq + Hash`.  Mapping goes both ways.
+/// A value expires once it has not been integerised or touched for longer
+/// than the time to live.  Expired values are not found any more, they are
+/// removed by `purge_expired` or when they are integerised again, and the
+/// integers of removed values are reused for values that are integerised later.
+///
+/// Since integers are reused, this structure does not implement `Integeriser`.
+///
+/// Every method that depends on the current time has a variant with the
+/// suffix `_at` that takes the current time as an argument instead.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use integeriser::expiring::ExpiringIntegeriser;
+///
+/// let mut integeriser = ExpiringIntegeriser::new(Duration::from_secs(50));
+/// let start = Instant::now();
+/// let at = |secs| start + Duration::from_secs(secs);
+///
+/// let k = integeriser.integerise_at("session-1".to_string(), at(0));
+/// let l = integeriser.integerise_at("session-2".to_string(), at(0));
+/// assert!(integeriser.touch_at(k, at(30)));
+///
+/// assert_eq!(integeriser.find_value_at(l, at(50)), Some(&"session-2".to_string()));
+/// assert_eq!(integeriser.find_value_at(l, at(60)), None);
+/// assert_eq!(integeriser.find_key_at(&"session-1".to_string(), at(60)), Some(k));
+/// assert_eq!(integeriser.purge_expired_at(at(60)), vec![l]);
+///
+/// // the integer of the expired value is reused
+/// assert_eq!(integeriser.integerise_at("session-3".to_string(), at(60)), l);
+/// assert_eq!(integeriser.size(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExpiringIntegeriser<A: Eq + Hash> {
+    map: Vec<Option<(A, Instant)>>,
+    rmap: HashMap<A, usize>,
+    free: Vec<usize>,
+    ttl: Duration,
+}
+
+impl<A: Eq + Hash> ExpiringIntegeriser<A> {
+    /// Constructs a new, empty `ExpiringIntegeriser<A>` whose values expire
+    /// after being unused for longer than `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        ExpiringIntegeriser {
+            map: Vec::new(),
+            rmap: HashMap::new(),
+            free: Vec::new(),
+            ttl,
+        }
+    }
+
+    /// The time to live of the values.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn is_live(&self, k: usize, now: Instant) -> bool {
+        match self.map.get(k) {
+            Some(&Some((_, touched))) => now.saturating_duration_since(touched) <= self.ttl,
+            _ => false,
+        }
+    }
+
+    fn remove(&mut self, k: usize) {
+        if let Some((a, _)) = self.map[k].take() {
+            self.rmap.remove(&a);
+        }
+    }
+
+    /// Returns a unique integer for the given value `a` and resets its time
+    /// to live.  If `a` is not stored or has expired, its integer is the
+    /// smallest one that has been freed, or the next consecutive one if
+    /// there is none.
+    pub fn integerise(&mut self, a: A) -> usize
+    where
+        A: Clone,
+    {
+        self.integerise_at(a, Instant::now())
+    }
+
+    /// `integerise` at the time `now`.
+    pub fn integerise_at(&mut self, a: A, now: Instant) -> usize
+    where
+        A: Clone,
+    {
+        if let Some(&k) = self.rmap.get(&a) {
+            if self.is_live(k, now) {
+                if let Some((_, ref mut touched)) = self.map[k] {
+                    *touched = now;
+                }
+                return k;
+            }
+            self.remove(k);
+            count!("integeriser_evictions_total", 1);
+            self.free.push(k);
+            self.free.sort_unstable_by(|k, l| l.cmp(k));
+        }
+
+        let k = match self.free.pop() {
+            Some(k) => {
+                self.map[k] = Some((a.clone(), now));
+                k
+            }
+            None => {
+                self.map.push(Some((a.clone(), now)));
+                self.map.len() - 1
+            }
+        };
+        self.rmap.insert(a, k);
+        k
+    }
+
+    /// Resets the time to live of the value with the integer `k`.
+    /// Returns `false` if there is no such value or if it has expired.
+    pub fn touch(&mut self, k: usize) -> bool {
+        self.touch_at(k, Instant::now())
+    }
+
+    /// `touch` at the time `now`.
+    pub fn touch_at(&mut self, k: usize, now: Instant) -> bool {
+        if !self.is_live(k, now) {
+            return false;
+        }
+        if let Some((_, ref mut touched)) = self.map[k] {
+            *touched = now;
+        }
+        true
+    }
+
+    /// Lookup the value that corresponds to the integer `k: usize`, unless it has expired.
+    pub fn find_value(&self, k: usize) -> Option<&A> {
+        self.find_value_at(k, Instant::now())
+    }
+
+    /// `find_value` at the time `now`.
+    pub fn find_value_at(&self, k: usize, now: Instant) -> Option<&A> {
+        if self.is_live(k, now) {
+            self.map[k].as_ref().map(|(a, _)| a)
+        } else {
+            None
+        }
+    }
+
+    /// Lookup the integer that corresponds to the value `a: A`, unless it has expired.
+    pub fn find_key(&self, a: &A) -> Option<usize> {
+        self.find_key_at(a, Instant::now())
+    }
+
+    /// `find_key` at the time `now`.
+    pub fn find_key_at(&self, a: &A, now: Instant) -> Option<usize> {
+        self.rmap.get(a).cloned().filter(|&k| self.is_live(k, now))
+    }
+
+    /// Number of values that are currently stored in the integeriser,
+    /// including expired values that have not been removed yet.
+    pub fn size(&self) -> usize {
+        self.rmap.len()
+    }
+
+    /// Removes all expired values and returns their integers in ascending order.
+    pub fn purge_expired(&mut self) -> Vec<usize> {
+        self.purge_expired_at(Instant::now())
+    }
+
+    /// `purge_expired` at the time `now`.
+    pub fn purge_expired_at(&mut self, now: Instant) -> Vec<usize> {
+        let expired: Vec<usize> = (0..self.map.len())
+            .filter(|&k| self.map[k].is_some() && !self.is_live(k, now))
+            .collect();
+        for &k in &expired {
+            self.remove(k);
+        }
+
+        count!("integeriser_evictions_total", expired.len());
+        self.free.extend(expired.iter().cloned());
+        self.free.sort_unstable_by(|k, l| l.cmp(k));
+        expired
+    }
+}
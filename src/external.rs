@@ -0,0 +1,178 @@
+//! Building vocabularies that do not fit into main memory.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of the next temporary file, unique within the process.
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Collects values in batches of bounded size, spills every full batch as a
+/// sorted run to a temporary file and finally merges all runs, assigning
+/// consecutive integers to the distinct values in ascending (byte-wise) order.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::external::ExternalBuilder;
+///
+/// let mut builder = ExternalBuilder::new(std::env::temp_dir(), 8);
+/// for w in "the cat saw the dog and the dog saw a cat".split(' ') {
+///     builder.push(w).unwrap();
+/// }
+///
+/// let mut vocab = Vec::new();
+/// let size = builder.finish(|k, value| {
+///     vocab.push((k, String::from_utf8(value.to_vec()).unwrap()));
+///     Ok(())
+/// }).unwrap();
+///
+/// assert_eq!(size, 6);
+/// assert_eq!(vocab[0], (0, "a".to_string()));
+/// assert_eq!(vocab[5], (5, "the".to_string()));
+/// ```
+#[derive(Debug)]
+pub struct ExternalBuilder {
+    dir: PathBuf,
+    batch_bytes: usize,
+    batch: BTreeSet<Vec<u8>>,
+    batch_size: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl ExternalBuilder {
+    /// Constructs a builder that stores its temporary files in `dir` and
+    /// spills a batch once its values take up more than `batch_bytes` bytes.
+    pub fn new<P: Into<PathBuf>>(dir: P, batch_bytes: usize) -> Self {
+        ExternalBuilder {
+            dir: dir.into(),
+            batch_bytes,
+            batch: BTreeSet::new(),
+            batch_size: 0,
+            runs: Vec::new(),
+        }
+    }
+
+    /// Adds a value to the vocabulary.
+    pub fn push<A: AsRef<[u8]>>(&mut self, value: A) -> io::Result<()> {
+        let value = value.as_ref();
+        if !self.batch.contains(value) {
+            self.batch_size += value.len();
+            self.batch.insert(value.to_vec());
+            if self.batch_size > self.batch_bytes {
+                self.spill()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the batch to a new run.  If that fails, the batch is kept and
+    /// the incomplete run is removed.
+    fn spill(&mut self) -> io::Result<()> {
+        let path = self.dir.join(format!("integeriser-{}-{}.run", process::id(), NEXT_RUN.fetch_add(1, Ordering::Relaxed)));
+        if let Err(e) = self.write_run(&path) {
+            let _ = fs::remove_file(&path);
+            return Err(e);
+        }
+        self.runs.push(path);
+        self.batch.clear();
+        self.batch_size = 0;
+        Ok(())
+    }
+
+    fn write_run(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for value in &self.batch {
+            writer.write_all(&(value.len() as u64).to_le_bytes())?;
+            writer.write_all(value)?;
+        }
+        writer.flush()
+    }
+
+    /// Merges all values and calls `f` with every distinct value and its
+    /// integer, in ascending order.  Returns the number of distinct values.
+    pub fn finish<F>(mut self, mut f: F) -> io::Result<usize>
+    where
+        F: FnMut(usize, &[u8]) -> io::Result<()>,
+    {
+        trace_span!("build", runs = self.runs.len());
+        if !self.batch.is_empty() {
+            self.spill()?;
+        }
+
+        let mut readers = Vec::with_capacity(self.runs.len());
+        let mut heap = BinaryHeap::with_capacity(self.runs.len());
+        for (i, path) in self.runs.iter().enumerate() {
+            let mut reader = BufReader::new(File::open(path)?);
+            if let Some(value) = read_value(&mut reader)? {
+                heap.push(Reverse((value, i)));
+            }
+            readers.push(reader);
+        }
+
+        let mut size = 0;
+        let mut last: Option<Vec<u8>> = None;
+        while let Some(Reverse((value, i))) = heap.pop() {
+            if let Some(next) = read_value(&mut readers[i])? {
+                heap.push(Reverse((next, i)));
+            }
+            if last.as_ref() != Some(&value) {
+                f(size, &value)?;
+                size += 1;
+                last = Some(value);
+            }
+        }
+
+        trace_event!(values = size, "merged runs");
+        Ok(size)
+    }
+}
+
+impl Drop for ExternalBuilder {
+    fn drop(&mut self) {
+        for path in &self.runs {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn read_value<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 8];
+    match reader.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut value = vec![0; u64::from_le_bytes(len) as usize];
+    reader.read_exact(&mut value)?;
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternalBuilder;
+
+    #[test]
+    fn failed_spills_keep_the_batch() {
+        let dir = std::env::temp_dir();
+        let mut builder = ExternalBuilder::new(dir.join("integeriser-external-missing"), 1);
+        builder.push("b").unwrap();
+        assert!(builder.push("a").is_err());
+        assert!(builder.runs.is_empty());
+
+        builder.dir = dir;
+        let mut values = Vec::new();
+        let size = builder
+            .finish(|_, value| {
+                values.push(value.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(values, vec![b"a".to_vec(), b"b".to_vec()]);
+    }
+}
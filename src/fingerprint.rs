@@ -0,0 +1,161 @@
+//! Integerising values by their digests, without storing the values.
+
+use std::collections::HashMap;
+use std::collections::hash_map;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use blake3;
+
+/// `Hasher` that feeds all written bytes into BLAKE3; `finish` returns the
+/// first 8 bytes of the digest.
+struct DigestHasher(blake3::Hasher);
+
+impl Hasher for DigestHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.0.finalize().as_bytes()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+/// 256-bit digest of a value, computed by BLAKE3 over the data fed to its
+/// `Hash` implementation, split into the 128-bit fingerprint and 128 check bits.
+fn digest<A: Hash + ?Sized>(a: &A) -> (u128, u128) {
+    let mut hasher = DigestHasher(blake3::Hasher::new());
+    a.hash(&mut hasher);
+    let digest = hasher.0.finalize();
+    let (fingerprint, check) = digest.as_bytes().split_at(16);
+    let half = |bytes: &[u8]| {
+        let mut half = [0; 16];
+        half.copy_from_slice(bytes);
+        u128::from_le_bytes(half)
+    };
+    (half(fingerprint), half(check))
+}
+
+/// 128-bit digest of a value, computed by BLAKE3 over the data fed to its
+/// `Hash` implementation.  Since `Hash` implementations may change between
+/// Rust versions, fingerprints should not be persisted.
+pub fn fingerprint<A: Hash + ?Sized>(a: &A) -> u128 {
+    digest(a).0
+}
+
+/// Error for a value whose fingerprint equals that of a different value
+/// that has already been integerised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Collision {
+    /// The 128-bit fingerprint shared by both values.
+    pub fingerprint: u128,
+    /// Integer of the value that has been integerised first.
+    pub existing: usize,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fingerprint {:032x} is shared with the value with integer {}", self.fingerprint, self.existing)
+    }
+}
+
+impl Error for Collision {}
+
+/// Structure that maps to every element of type `A` an integer of type
+/// `usize`, but only stores a 256-bit digest of every value.  Values can
+/// therefore not be looked up by their integers.  Values are identified by
+/// the first 128 bits of their digests (their fingerprints), and the other
+/// 128 bits detect distinct values with equal fingerprints, which are
+/// astronomically unlikely.
+///
+/// Since its integers cannot be mapped back to values, this structure does
+/// not implement `Integeriser`, but has methods of the same names.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::fingerprint::FingerprintIntegeriser;
+///
+/// let mut integeriser = FingerprintIntegeriser::new();
+///
+/// let blob = vec![0u8; 1 << 20];
+/// let k = integeriser.integerise(&blob[..]);
+///
+/// assert_eq!(integeriser.integerise(&vec![0u8; 1 << 20][..]), k);
+/// assert_eq!(integeriser.find_key(&blob[..]), Some(k));
+/// assert_eq!(integeriser.find_key(&blob[1..]), None);
+/// assert_eq!(integeriser.size(), 1);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FingerprintIntegeriser {
+    rmap: HashMap<u128, (usize, u128)>,
+}
+
+impl FingerprintIntegeriser {
+    /// Constructs a new, empty `FingerprintIntegeriser`.
+    pub fn new() -> Self {
+        FingerprintIntegeriser { rmap: HashMap::new() }
+    }
+
+    /// Returns a unique integer for the given value `a`, see `Integeriser::integerise`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fingerprint of `a` collides with that of another value.
+    pub fn integerise<A: Hash + ?Sized>(&mut self, a: &A) -> usize {
+        match self.try_integerise(a) {
+            Ok(k) => k,
+            Err(collision) => panic!("{}", collision),
+        }
+    }
+
+    /// Returns a unique integer for the given value `a`, or an error if the
+    /// fingerprint of `a` collides with that of another value.
+    pub fn try_integerise<A: Hash + ?Sized>(&mut self, a: &A) -> Result<usize, Collision> {
+        let (fingerprint, check) = digest(a);
+        let size = self.rmap.len();
+        match self.rmap.entry(fingerprint) {
+            hash_map::Entry::Occupied(e) => {
+                let (existing, existing_check) = *e.get();
+                if existing_check == check {
+                    Ok(existing)
+                } else {
+                    Err(Collision { fingerprint, existing })
+                }
+            }
+            hash_map::Entry::Vacant(e) => Ok(e.insert((size, check)).0),
+        }
+    }
+
+    /// Lookup the integer that corresponds to the value `a`.
+    pub fn find_key<A: Hash + ?Sized>(&self, a: &A) -> Option<usize> {
+        let (fingerprint, check) = digest(a);
+        match self.rmap.get(&fingerprint) {
+            Some(&(k, existing_check)) if existing_check == check => Some(k),
+            _ => None,
+        }
+    }
+
+    /// Number of distinct values that have been integerised.
+    pub fn size(&self) -> usize {
+        self.rmap.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{Hash, Hasher};
+
+    use super::{fingerprint, DigestHasher};
+    use blake3;
+
+    #[test]
+    fn finish_returns_the_start_of_the_fingerprint() {
+        let mut hasher = DigestHasher(blake3::Hasher::new());
+        "value".hash(&mut hasher);
+        assert_eq!(hasher.finish(), fingerprint("value") as u64);
+    }
+}
@@ -0,0 +1,85 @@
+//! Integerisers for floating-point values, using the wrappers of the
+//! `ordered-float` crate to provide `Eq`, `Ord` and `Hash`.
+//!
+//! `OrderedFloat` considers all NaNs to be equal and `-0.0` to be equal
+//! to `0.0`, so these values share one integer.
+//!
+//! This module has no `From` conversions of its own: the wrappers are types
+//! of `ordered-float`, which already converts with `From<f32>` and
+//! `From<f64>` into `OrderedFloat`, with `TryFrom<f32>` and `TryFrom<f64>`
+//! into `NotNan`, and back into `f32` and `f64` with `From`.  Plain values
+//! can therefore be passed to the integerisers with `into()` and
+//! `try_into()`, or with the methods of `FloatIntegeriser`.
+//!
+//! ```
+//! use std::convert::TryInto;
+//! use integeriser::Integeriser;
+//! use integeriser::float::{FloatHashIntegeriser, NotNanBTreeIntegeriser};
+//!
+//! let mut floats: FloatHashIntegeriser<f32> = FloatHashIntegeriser::new();
+//! let k = floats.integerise(1.5.into());
+//! assert_eq!(floats.find_value(k).map(|&x| f32::from(x)), Some(1.5));
+//!
+//! let mut not_nans: NotNanBTreeIntegeriser<f64> = NotNanBTreeIntegeriser::new();
+//! assert_eq!(not_nans.integerise(2.5.try_into().unwrap()), 0);
+//! assert!(TryInto::<ordered_float::NotNan<f64>>::try_into(f64::NAN).is_err());
+//! ```
+
+use ordered_float::{Float, NotNan, OrderedFloat};
+
+use {BTreeIntegeriser, HashIntegeriser, Integeriser};
+
+/// `HashIntegeriser` for floating-point values.
+pub type FloatHashIntegeriser<F> = HashIntegeriser<OrderedFloat<F>>;
+
+/// `BTreeIntegeriser` for floating-point values.
+pub type FloatBTreeIntegeriser<F> = BTreeIntegeriser<OrderedFloat<F>>;
+
+/// `HashIntegeriser` for floating-point values that are not NaN.
+pub type NotNanHashIntegeriser<F> = HashIntegeriser<NotNan<F>>;
+
+/// `BTreeIntegeriser` for floating-point values that are not NaN.
+pub type NotNanBTreeIntegeriser<F> = BTreeIntegeriser<NotNan<F>>;
+
+/// Methods of `Integeriser` that take and return plain floating-point
+/// values instead of `OrderedFloat`s.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::Integeriser;
+/// use integeriser::float::{FloatHashIntegeriser, FloatIntegeriser};
+///
+/// let mut integeriser: FloatHashIntegeriser<f64> = FloatHashIntegeriser::new();
+///
+/// let k = integeriser.integerise_float(0.5);
+///
+/// assert_eq!(integeriser.integerise_float(f64::NAN), 1);
+/// assert_eq!(integeriser.find_key_float(0.5), Some(k));
+/// assert_eq!(integeriser.find_float(k), Some(0.5));
+/// assert_eq!(integeriser.size(), 2);
+/// ```
+pub trait FloatIntegeriser<F: Float> {
+    /// Returns a unique integer for the value `x`, see `Integeriser::integerise`.
+    fn integerise_float(&mut self, x: F) -> usize;
+
+    /// Lookup the integer that corresponds to the value `x`.
+    fn find_key_float(&self, x: F) -> Option<usize>;
+
+    /// Lookup the value that corresponds to the integer `k`.
+    fn find_float(&self, k: usize) -> Option<F>;
+}
+
+impl<F: Float, I: Integeriser<Item = OrderedFloat<F>>> FloatIntegeriser<F> for I {
+    fn integerise_float(&mut self, x: F) -> usize {
+        self.integerise(OrderedFloat(x))
+    }
+
+    fn find_key_float(&self, x: F) -> Option<usize> {
+        self.find_key(&OrderedFloat(x))
+    }
+
+    fn find_float(&self, k: usize) -> Option<F> {
+        self.find_value(k).map(|x| x.0)
+    }
+}
@@ -0,0 +1,178 @@
+//! Approximate lookup of strings stored in an integeriser.
+
+use std::collections::BTreeMap;
+
+use Integeriser;
+
+#[derive(Clone, Debug)]
+struct Node {
+    chars: Vec<char>,
+    id: usize,
+    children: BTreeMap<usize, usize>,
+}
+
+/// BK-tree over the values of a string integeriser w.r.t. the Levenshtein
+/// distance (counted in `char`s).
+/// The tree is a snapshot: values that are integerised after its
+/// construction are not known to it.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::fuzzy::FuzzyIndex;
+///
+/// let mut lexicon = HashIntegeriser::new();
+/// let colour = lexicon.integerise("colour");
+/// let color = lexicon.integerise("color");
+/// lexicon.integerise("collar");
+///
+/// let index = FuzzyIndex::new(&lexicon);
+///
+/// assert_eq!(index.find_key_fuzzy("colr", 1), vec![(color, 1)]);
+/// assert_eq!(index.find_key_fuzzy("colour", 1), vec![(colour, 0), (color, 1)]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyIndex {
+    nodes: Vec<Node>,
+}
+
+impl FuzzyIndex {
+    /// Builds the tree over all values currently stored in `integeriser`.
+    pub fn new<I, S>(integeriser: &I) -> Self
+    where
+        I: Integeriser<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut index = FuzzyIndex { nodes: Vec::new() };
+        for k in 0..integeriser.size() {
+            if let Some(value) = integeriser.find_value(k) {
+                index.insert(value.as_ref().chars().collect(), k);
+            }
+        }
+        index
+    }
+
+    fn insert(&mut self, chars: Vec<char>, id: usize) {
+        let next = self.nodes.len();
+        if next > 0 {
+            let mut node = 0;
+            loop {
+                let d = levenshtein(&self.nodes[node].chars, &chars);
+                match self.nodes[node].children.get(&d) {
+                    Some(&child) => node = child,
+                    None => {
+                        self.nodes[node].children.insert(d, next);
+                        break;
+                    }
+                }
+            }
+        }
+        self.nodes.push(Node { chars, id, children: BTreeMap::new() });
+    }
+
+    /// Returns the integers of all values whose distance to `s` is at most
+    /// `max_edits`, together with that distance.
+    /// The result is sorted by distance and then by integer.
+    pub fn find_key_fuzzy(&self, s: &str, max_edits: usize) -> Vec<(usize, usize)> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut result = Vec::new();
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let d = levenshtein(&node.chars, &chars);
+            if d <= max_edits {
+                result.push((node.id, d));
+            }
+            // by the triangle inequality, only children at distance d ± max_edits can match
+            let lower = d.saturating_sub(max_edits);
+            stack.extend(node.children.range(lower..=d + max_edits).map(|(_, &child)| child));
+        }
+
+        result.sort_by_key(|&(k, d)| (d, k));
+        result
+    }
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{levenshtein, FuzzyIndex};
+    use {HashIntegeriser, Integeriser};
+
+    const WORDS: &[&str] = &[
+        "book", "books", "boo", "boon", "cook", "cake", "cape", "cart", "", "a", "ab", "abc", "back", "brook",
+    ];
+
+    fn lexicon() -> HashIntegeriser<&'static str> {
+        let mut lexicon = HashIntegeriser::new();
+        for w in WORDS {
+            lexicon.integerise(*w);
+        }
+        lexicon
+    }
+
+    fn distance(a: &str, b: &str) -> usize {
+        levenshtein(&a.chars().collect::<Vec<_>>(), &b.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn distances_count_chars() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("mañana", "manana"), 1);
+    }
+
+    #[test]
+    fn pruning_keeps_all_matches() {
+        let index = FuzzyIndex::new(&lexicon());
+        for query in &["book", "bok", "cape", "xyz", "", "brooks", "ca"] {
+            for max_edits in 0..5 {
+                let mut expected: Vec<(usize, usize)> = WORDS
+                    .iter()
+                    .enumerate()
+                    .map(|(k, w)| (k, distance(w, query)))
+                    .filter(|&(_, d)| d <= max_edits)
+                    .collect();
+                expected.sort_by_key(|&(k, d)| (d, k));
+                assert_eq!(index.find_key_fuzzy(query, max_edits), expected, "{:?} within {}", query, max_edits);
+            }
+        }
+    }
+
+    #[test]
+    fn children_are_keyed_by_distance() {
+        let index = FuzzyIndex::new(&lexicon());
+        for (i, node) in index.nodes.iter().enumerate() {
+            for (&d, &child) in &node.children {
+                assert!(child > i);
+                assert_eq!(levenshtein(&node.chars, &index.nodes[child].chars), d);
+            }
+        }
+        // of the values at distance 1 from the root "book", only the first
+        // is its child, the others are inserted below that child
+        assert_eq!(index.nodes[index.nodes[0].children[&1]].chars, "books".chars().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn empty_indices_find_nothing() {
+        let index = FuzzyIndex::new(&HashIntegeriser::<String>::new());
+        assert!(index.find_key_fuzzy("", 3).is_empty());
+        assert!(FuzzyIndex::default().find_key_fuzzy("a", 1).is_empty());
+    }
+}
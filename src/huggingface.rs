@@ -0,0 +1,227 @@
+//! Reading and writing vocabularies in the `vocab.json` format used by
+//! HuggingFace tokenizers, i.e. a JSON object that maps every token to its id.
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde_json;
+
+use portable::{self, Overflow};
+use Integeriser;
+
+/// Errors that can occur while reading or writing a `vocab.json` file.
+#[derive(Debug)]
+pub enum VocabError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The input is not a JSON object from strings to non-negative integers.
+    Json(serde_json::Error),
+    /// Two tokens are mapped to the same id.
+    DuplicateId(usize),
+    /// The token is mapped to an id more than once.
+    DuplicateToken(String),
+    /// No token is mapped to the given id, although larger ids occur.
+    MissingId(usize),
+    /// An id does not fit into `usize` on this machine.
+    Overflow(Overflow),
+}
+
+impl fmt::Display for VocabError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VocabError::Io(ref e) => write!(f, "i/o error: {}", e),
+            VocabError::Json(ref e) => write!(f, "malformed vocabulary: {}", e),
+            VocabError::DuplicateId(k) => write!(f, "id {} is assigned to more than one token", k),
+            VocabError::DuplicateToken(ref token) => write!(f, "token {:?} occurs more than once", token),
+            VocabError::MissingId(k) => write!(f, "id {} is not assigned to any token", k),
+            VocabError::Overflow(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for VocabError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            VocabError::Io(ref e) => Some(e),
+            VocabError::Json(ref e) => Some(e),
+            VocabError::Overflow(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for VocabError {
+    fn from(e: io::Error) -> Self {
+        VocabError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for VocabError {
+    fn from(e: serde_json::Error) -> Self {
+        VocabError::Json(e)
+    }
+}
+
+impl From<Overflow> for VocabError {
+    fn from(e: Overflow) -> Self {
+        VocabError::Overflow(e)
+    }
+}
+
+/// Entries of a JSON object from tokens to ids, in the order of the file.
+struct Entries(Vec<(String, u64)>);
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+struct EntriesVisitor;
+
+impl<'de> Visitor<'de> for EntriesVisitor {
+    type Value = Entries;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map from tokens to ids")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Entries, M::Error> {
+        let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(entry) = access.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Entries(entries))
+    }
+}
+
+/// Reads a `vocab.json` file into a fresh integeriser.
+/// The ids in the file must be exactly `0, …, n-1`; every token (including
+/// special tokens such as `[UNK]` or `<s>`) keeps the id it has in the file.
+/// Fails if a token occurs more than once, even with the same id.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::huggingface::read_vocab;
+///
+/// let json = r#"{"[UNK]": 0, "hello": 2, "[CLS]": 1}"#;
+/// let vocab: HashIntegeriser<String> = read_vocab(json.as_bytes()).unwrap();
+///
+/// assert_eq!(vocab.find_key(&"[CLS]".to_string()), Some(1));
+/// assert_eq!(vocab.find_value(2), Some(&"hello".to_string()));
+///
+/// assert!(read_vocab::<_, HashIntegeriser<String>>(r#"{"a": 0, "b": 2}"#.as_bytes()).is_err());
+/// assert!(read_vocab::<_, HashIntegeriser<String>>(r#"{"a": 0, "a": 1}"#.as_bytes()).is_err());
+/// ```
+pub fn read_vocab<R, I>(reader: R) -> Result<I, VocabError>
+where
+    R: Read,
+    I: Integeriser<Item = String> + Default,
+{
+    let Entries(entries) = serde_json::from_reader(reader)?;
+
+    let mut seen = HashSet::with_capacity(entries.len());
+    let mut tokens: Vec<Option<String>> = vec![None; entries.len()];
+    for (token, k) in entries {
+        if !seen.insert(token.clone()) {
+            return Err(VocabError::DuplicateToken(token));
+        }
+        let k = portable::narrow(k)?;
+        match tokens.get_mut(k) {
+            Some(slot @ &mut None) => *slot = Some(token),
+            Some(&mut Some(_)) => return Err(VocabError::DuplicateId(k)),
+            // ids beyond the number of tokens imply a gap, which is reported below
+            None => (),
+        }
+    }
+
+    let mut integeriser = I::default();
+    for (k, token) in tokens.into_iter().enumerate() {
+        match token {
+            Some(token) => {
+                integeriser.integerise(token);
+            }
+            None => return Err(VocabError::MissingId(k)),
+        }
+    }
+
+    Ok(integeriser)
+}
+
+/// Writes the values of an integeriser as a `vocab.json` file.
+/// Entries are written in the order of their ids and without whitespace,
+/// which is the layout produced by the `tokenizers` library.
+/// Fails if an id below the size of `integeriser` has no value.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::huggingface::write_vocab;
+///
+/// let mut vocab = HashIntegeriser::new();
+/// vocab.integerise("[UNK]".to_string());
+/// vocab.integerise("hello".to_string());
+///
+/// let mut json = Vec::new();
+/// write_vocab(&vocab, &mut json).unwrap();
+///
+/// assert_eq!(json, br#"{"[UNK]":0,"hello":1}"#);
+/// ```
+pub fn write_vocab<I, W>(integeriser: &I, mut writer: W) -> Result<(), VocabError>
+where
+    I: Integeriser<Item = String>,
+    W: Write,
+{
+    writer.write_all(b"{")?;
+    for k in 0..integeriser.size() {
+        if k > 0 {
+            writer.write_all(b",")?;
+        }
+        let token = integeriser.find_value(k).ok_or(VocabError::MissingId(k))?;
+        serde_json::to_writer(&mut writer, token)?;
+        write!(writer, ":{}", portable::widen(k))?;
+    }
+    writer.write_all(b"}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_vocab, VocabError};
+    use HashIntegeriser;
+
+    fn read(json: &str) -> Result<HashIntegeriser<String>, VocabError> {
+        read_vocab(json.as_bytes())
+    }
+
+    #[test]
+    fn malformed_vocabularies_are_rejected() {
+        match read(r#"{"a": 0, "b": 1, "a": 2}"#) {
+            Err(VocabError::DuplicateToken(ref token)) if token == "a" => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match read(r#"{"a": 0, "a": 0}"#) {
+            Err(VocabError::DuplicateToken(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match read(r#"{"a": 1, "b": 1, "c": 0}"#) {
+            Err(VocabError::DuplicateId(1)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match read(r#"{"a": 0, "b": 3, "c": 1}"#) {
+            Err(VocabError::MissingId(2)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        match read(r#"{"a": -1}"#) {
+            Err(VocabError::Json(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(read("{}").unwrap(), HashIntegeriser::new());
+    }
+}
@@ -2,15 +2,146 @@ use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap};
 use std::collections::btree_map;
 use std::collections::hash_map;
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt;
 use std::hash::{Hash, Hasher, BuildHasher};
+use std::marker::PhantomData;
 use std::vec::Vec;
 
+use block::Blocks;
+use storage::Storage;
+
 #[cfg(feature = "serialisation")]
 extern crate serde;
 
 #[cfg(feature = "fnv-hashintegeriser")]
 extern crate fnv;
 
+#[cfg(feature = "indexmap-integeriser")]
+extern crate indexmap;
+
+#[cfg(feature = "heapless-integeriser")]
+extern crate heapless;
+
+#[cfg(feature = "persistent-integeriser")]
+extern crate im;
+
+#[cfg(any(feature = "huggingface", feature = "persistence"))]
+extern crate serde_json;
+
+#[cfg(feature = "scanner")]
+extern crate aho_corasick;
+
+#[cfg(feature = "normalisation")]
+extern crate unicode_normalization;
+
+#[cfg(feature = "float")]
+extern crate ordered_float;
+
+#[cfg(feature = "compact-strings")]
+extern crate compact_str;
+
+#[cfg(any(feature = "content-hash", feature = "fingerprint-integeriser"))]
+extern crate blake3;
+
+#[cfg(feature = "gzip")]
+extern crate flate2;
+
+#[cfg(feature = "zstd-compression")]
+extern crate zstd;
+
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+#[cfg(feature = "metrics")]
+extern crate metrics;
+
+#[cfg(feature = "ndarray")]
+extern crate ndarray;
+
+#[cfg(feature = "sprs")]
+extern crate sprs;
+
+#[cfg(feature = "redis")]
+extern crate redis;
+
+/// Enters a `tracing` span for the rest of the enclosing block if the
+/// feature `tracing` is enabled.
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($arg)*).entered();
+    };
+}
+
+/// Emits a `tracing` event if the feature `tracing` is enabled.
+macro_rules! trace_event {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::info!($($arg)*);
+    };
+}
+
+/// Increases the `metrics` counter `$name` by `$n` if the feature `metrics` is enabled.
+macro_rules! count {
+    ($name:expr, $n:expr) => {
+        #[cfg(feature = "metrics")]
+        metrics::counter!($name).increment($n as u64);
+    };
+}
+
+pub mod arena;
+pub mod binned;
+pub mod block;
+pub mod bloom;
+pub mod broadcast;
+pub mod budget;
+pub mod canonical;
+pub mod cell;
+pub mod cooccurrence;
+#[cfg(feature = "content-hash")]
+pub mod digest;
+pub mod either;
+pub mod embedding;
+pub mod expiring;
+pub mod external;
+#[cfg(feature = "fingerprint-integeriser")]
+pub mod fingerprint;
+#[cfg(feature = "float")]
+pub mod float;
+pub mod fuzzy;
+#[cfg(feature = "huggingface")]
+pub mod huggingface;
+#[cfg(feature = "metrics")]
+pub mod metered;
+pub mod ngrams;
+#[cfg(feature = "normalisation")]
+pub mod normalise;
+#[cfg(feature = "persistence")]
+pub mod persist;
+#[cfg(feature = "serialisation")]
+pub mod portable;
+#[cfg(feature = "scanner")]
+pub mod scanner;
+pub mod registry;
+pub mod segment;
+#[cfg(feature = "redis")]
+pub mod shared;
+pub mod sketch;
+pub mod snapshot;
+pub mod sparse;
+pub mod static_integeriser;
+#[cfg(feature = "serialisation")]
+pub mod stateful;
+pub mod storage;
+pub mod stream;
+pub mod substring;
+pub mod timestamped;
+#[cfg(feature = "tracing")]
+pub mod traced;
+pub mod weak;
+
 pub trait Integeriser {
     type Item;
 
@@ -28,10 +159,212 @@ pub trait Integeriser {
 
     /// Number of distinct values `a: Self::Item` that are stored in the iterator.
     fn size(&self) -> usize;
+
+    /// Whether no values are stored in the integeriser.
+    fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    /// The value that corresponds to the integer `k`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` does not correspond to any value; the message contains
+    /// `k` and the size of the integeriser.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// let k = integeriser.integerise("a");
+    ///
+    /// assert_eq!(integeriser.value(k), &"a");
+    /// ```
+    ///
+    /// ```should_panic
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let integeriser: HashIntegeriser<&str> = HashIntegeriser::new();
+    /// integeriser.value(3); // panics: "unknown id 3 in an integeriser with 0 values"
+    /// ```
+    fn value(&self, k: usize) -> &Self::Item {
+        match self.find_value(k) {
+            Some(a) => a,
+            None => panic!("unknown id {} in an integeriser with {} values", k, self.size()),
+        }
+    }
+
+    /// The integer that corresponds to the value `a`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is not stored in the integeriser; the message contains
+    /// `a` and the size of the integeriser.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// integeriser.integerise("a");
+    /// let k = integeriser.integerise("b");
+    ///
+    /// assert_eq!(integeriser.key(&"b"), k);
+    /// ```
+    fn key(&self, a: &Self::Item) -> usize
+    where
+        Self::Item: fmt::Debug,
+    {
+        match self.find_key(a) {
+            Some(k) => k,
+            None => panic!("unknown value {:?} in an integeriser with {} values", a, self.size()),
+        }
+    }
+
+    /// Looks up the values of all integers in `ks` and joins them, separated
+    /// by `sep`, into a single `String`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser, UnknownId};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// let ks: Vec<usize> = "a rose is a rose".split(' ').map(|w| integeriser.integerise(w)).collect();
+    ///
+    /// assert_eq!(integeriser.decode_sequence(&ks, " "), Ok("a rose is a rose".to_string()));
+    /// assert_eq!(integeriser.decode_sequence(&[0, 7], " "), Err(UnknownId(7)));
+    /// ```
+    fn decode_sequence(&self, ks: &[usize], sep: &str) -> Result<String, UnknownId>
+    where
+        Self::Item: AsRef<str>,
+    {
+        let mut len = sep.len() * ks.len().saturating_sub(1);
+        for &k in ks {
+            len += self.find_value(k).ok_or(UnknownId(k))?.as_ref().len();
+        }
+
+        let mut result = String::with_capacity(len);
+        for (i, &k) in ks.iter().enumerate() {
+            if i > 0 {
+                result.push_str(sep);
+            }
+            result.push_str(self.find_value(k).ok_or(UnknownId(k))?.as_ref());
+        }
+        Ok(result)
+    }
+}
+
+/// Error for an integer that does not correspond to any value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnknownId(pub usize);
+
+impl fmt::Display for UnknownId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no value corresponds to the integer {}", self.0)
+    }
+}
+
+impl Error for UnknownId {}
+
+/// Error for appending an integeriser that shares a value with `self`.
+/// Contains the rejected integeriser.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Overlap<I> {
+    /// Integer of the shared value in `self`.
+    pub ours: usize,
+    /// Integer of the shared value in the rejected integeriser.
+    pub theirs: usize,
+    /// The rejected integeriser.
+    pub integeriser: I,
+}
+
+impl<I> fmt::Display for Overlap<I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "value {} of the appended integeriser is already integerised as {}", self.theirs, self.ours)
+    }
+}
+
+impl<I: fmt::Debug> Error for Overlap<I> {}
+
+/// Error for replacing the value of an integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ReplaceError {
+    /// No value corresponds to the integer.
+    UnknownId(usize),
+    /// The new value is already integerised as the given, different integer.
+    Duplicate(usize),
+}
+
+impl fmt::Display for ReplaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplaceError::UnknownId(k) => write!(f, "no value corresponds to the integer {}", k),
+            ReplaceError::Duplicate(k) => write!(f, "the new value is already integerised as {}", k),
+        }
+    }
+}
+
+impl Error for ReplaceError {}
+
+/// Error for reassembling an integeriser from parts that disagree on the
+/// value of an integer.  Contains the rejected parts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InconsistentParts<V, M> {
+    /// Smallest integer on which the parts disagree.
+    pub id: usize,
+    /// The rejected values.
+    pub values: V,
+    /// The rejected map from values to integers.
+    pub map: M,
+}
+
+impl<V, M> fmt::Display for InconsistentParts<V, M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the parts of the integeriser disagree on the integer {}", self.id)
+    }
+}
+
+impl<V: fmt::Debug, M: fmt::Debug> Error for InconsistentParts<V, M> {}
+
+/// Correspondence between the integers of an integeriser and the integers
+/// that its values have in an integeriser derived from it, see e.g.
+/// `HashIntegeriser::partition`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Remap {
+    ids: Vec<Option<usize>>,
+}
+
+impl Remap {
+    /// The new integer of the value with the old integer `k`, or `None` if
+    /// that value was not kept.
+    pub fn get(&self, k: usize) -> Option<usize> {
+        self.ids.get(k).cloned().and_then(|k| k)
+    }
+
+    /// Translates the old integers `ks` into the new ones.
+    /// Fails with the first integer whose value was not kept.
+    pub fn apply(&self, ks: &[usize]) -> Result<Vec<usize>, UnknownId> {
+        ks.iter().map(|&k| self.get(k).ok_or(UnknownId(k))).collect()
+    }
+
+    /// Number of old integers that are kept.
+    pub fn len(&self) -> usize {
+        self.ids.iter().filter(|k| k.is_some()).count()
+    }
+
+    /// Whether no old integer is kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Structure that maps to every element of type `A` an integer of type `usize`,
 /// given that `A: Eq + Hash`.  Mapping goes both ways.
+/// The values are kept in a `Storage` of type `V`, by default a `Vec<A>`.
 ///
 /// # Example
 ///
@@ -56,13 +389,15 @@ pub trait Integeriser {
 /// assert_ne!(arr1i[3], arr2i[3]);
 /// ```
 #[derive(Clone, Debug)]
-pub struct HashIntegeriser<A, S=hash_map::RandomState>
+pub struct HashIntegeriser<A, S=hash_map::RandomState, V=Vec<A>>
 where
     A: Eq + Hash,
     S: BuildHasher
 {
-    map: Vec<A>,
+    map: V,
     rmap: HashMap<A, usize, S>,
+    epoch: u64,
+    blocks: Blocks,
 }
 
 impl<A: Eq + Hash> HashIntegeriser<A, hash_map::RandomState> {
@@ -71,27 +406,424 @@ impl<A: Eq + Hash> HashIntegeriser<A, hash_map::RandomState> {
         HashIntegeriser {
             map: Vec::new(),
             rmap: HashMap::default(),
+            epoch: 0,
+            blocks: Blocks::default(),
+        }
+    }
+
+    /// Constructs a new, empty `HashIntegeriser<A>` with room for at least
+    /// `capacity` values before reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        HashIntegeriser {
+            map: Vec::with_capacity(capacity),
+            rmap: HashMap::with_capacity(capacity),
+            epoch: 0,
+            blocks: Blocks::default(),
         }
     }
 }
 
-impl<A: Eq + Hash, S: BuildHasher + Default> Default for HashIntegeriser<A, S> {
+impl<A: Eq + Hash, S: BuildHasher + Default, V: Default> Default for HashIntegeriser<A, S, V> {
     fn default() -> Self {
         HashIntegeriser {
-            map: Vec::new(),
-            rmap: HashMap::default()
+            map: V::default(),
+            rmap: HashMap::default(),
+            epoch: 0,
+            blocks: Blocks::default(),
         }
     }
 }
 
-impl<A: Eq + Hash, S: BuildHasher> HashIntegeriser<A, S> {
-    /// `Vec` containing all the values that have been stored in the iterator.
-    pub fn values(&self) -> &Vec<A> {
+impl<A: Eq + Hash, S: BuildHasher, V: Storage<A>> HashIntegeriser<A, S, V> {
+    /// `Storage` containing all the values that have been stored in the iterator.
+    pub fn values(&self) -> &V {
         &self.map
     }
+
+    /// Number of values the integeriser can hold without reallocating, i.e.
+    /// the smaller of the capacities of its storage and of its hash map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::with_capacity(100);
+    /// assert!(integeriser.is_empty());
+    /// assert!(integeriser.capacity() >= 100);
+    ///
+    /// integeriser.integerise("a");
+    /// assert!(!integeriser.is_empty());
+    /// ```
+    pub fn capacity(&self) -> usize {
+        self.map.capacity().min(self.rmap.capacity())
+    }
+
+    /// Reference to the `BuildHasher` of the hash map.
+    pub fn hasher(&self) -> &S {
+        self.rmap.hasher()
+    }
+
+    /// Takes `self` apart into the storage of its values and the hash map
+    /// from every value to its integer.
+    pub fn into_inner(self) -> (V, HashMap<A, usize, S>) {
+        (self.map, self.rmap)
+    }
+
+    /// Reassembles an integeriser from parts as returned by `into_inner`.
+    /// Fails if the hash map does not map exactly every value of the storage
+    /// to its index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// integeriser.integerise("a");
+    /// integeriser.integerise("b");
+    ///
+    /// let (mut values, rmap) = integeriser.clone().into_inner();
+    /// assert_eq!(HashIntegeriser::from_parts(values.clone(), rmap.clone()), Ok(integeriser));
+    ///
+    /// values.swap(0, 1);
+    /// assert_eq!(HashIntegeriser::from_parts(values, rmap).unwrap_err().id, 0);
+    /// ```
+    pub fn from_parts(map: V, rmap: HashMap<A, usize, S>) -> Result<Self, InconsistentParts<V, HashMap<A, usize, S>>> {
+        let inconsistency = (0..map.len())
+            .find(|&k| map.get(k).and_then(|a| rmap.get(a)) != Some(&k))
+            .or((rmap.len() != map.len()).then_some(map.len()));
+        match inconsistency {
+            Some(id) => Err(InconsistentParts { id, values: map, map: rmap }),
+            None => Ok(HashIntegeriser { map, rmap, epoch: 0, blocks: Blocks::default() }),
+        }
+    }
+
+    /// Number of changes to the stored values so far.  Every operation that
+    /// adds or removes values increases the epoch, so comparing it with an
+    /// earlier epoch tells whether data derived from the values is outdated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// integeriser.integerise("a");
+    /// let epoch = integeriser.epoch();
+    ///
+    /// integeriser.integerise("a");
+    /// assert_eq!(integeriser.epoch(), epoch);
+    ///
+    /// integeriser.integerise("b");
+    /// assert!(integeriser.epoch() > epoch);
+    /// ```
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Iterates over the integers and values of all values that have been
+    /// integerised after the first `checkpoint` values, e.g. with
+    /// `checkpoint` being an earlier result of `Integeriser::size`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// integeriser.integerise("a");
+    /// let checkpoint = integeriser.size();
+    /// integeriser.integerise("b");
+    /// integeriser.integerise("c");
+    ///
+    /// let delta: Vec<_> = integeriser.iter_since(checkpoint).collect();
+    /// assert_eq!(delta, vec![(1, &"b"), (2, &"c")]);
+    /// ```
+    pub fn iter_since<'a>(&'a self, checkpoint: usize) -> impl Iterator<Item = (usize, &'a A)> + 'a {
+        (checkpoint..self.map.len()).filter_map(move |k| self.map.get(k).map(|a| (k, a)))
+    }
+
+    /// Borrows `self` such that values can only be added, through a shared
+    /// reference, and the stored values can be referenced while values are added.
+    pub fn grow_only(&mut self) -> storage::GrowOnly<'_, A, S, V>
+    where
+        V: storage::StableStorage<A>,
+    {
+        storage::GrowOnly::new(self)
+    }
+
+    /// Lookup the integer that corresponds to a borrowed form of a value,
+    /// e.g. a `&[u8]` for `A = Vec<u8>` or a `&str` for `A = String`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// let k = integeriser.integerise(vec![0xca, 0xfe]);
+    ///
+    /// assert_eq!(integeriser.find_key_borrowed(&[0xca, 0xfe][..]), Some(k));
+    /// ```
+    pub fn find_key_borrowed<Q>(&self, q: &Q) -> Option<usize>
+    where
+        A: Borrow<Q>,
+        Q: ?Sized + Eq + Hash,
+    {
+        self.rmap.get(q).cloned()
+    }
+
+    /// Returns the integer and the length (in bytes) of the longest value
+    /// that is a non-empty prefix of `s`.
+    /// Every prefix of `s` is looked up separately; use
+    /// `segment::PrefixIndex` for many queries against a fixed vocabulary.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut lexicon = HashIntegeriser::new();
+    /// lexicon.integerise("New".to_string());
+    /// let new_york = lexicon.integerise("New York".to_string());
+    ///
+    /// assert_eq!(lexicon.longest_prefix_match("New York City"), Some((new_york, 8)));
+    /// assert_eq!(lexicon.longest_prefix_match("York"), None);
+    /// ```
+    pub fn longest_prefix_match(&self, s: &str) -> Option<(usize, usize)>
+    where
+        A: Borrow<str>,
+    {
+        s.char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .rev()
+            .filter_map(|len| self.rmap.get(&s[..len]).map(|&k| (k, len)))
+            .next()
+    }
+
+    /// Moves all values of `other` into `self`, where every value keeps its
+    /// integer shifted by the number of values in `self`, and returns that
+    /// shift.  Fails without changing `self` if both share a value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut shard0 = HashIntegeriser::new();
+    /// shard0.integerise("a");
+    /// let mut shard1 = HashIntegeriser::new();
+    /// shard1.integerise("b");
+    /// shard1.integerise("c");
+    ///
+    /// assert_eq!(shard0.append(shard1), Ok(1));
+    /// assert_eq!(shard0.find_key(&"c"), Some(2));
+    ///
+    /// let mut shard2 = HashIntegeriser::new();
+    /// shard2.integerise("c");
+    /// let overlap = shard0.append(shard2).unwrap_err();
+    /// assert_eq!((overlap.ours, overlap.theirs), (2, 0));
+    /// ```
+    pub fn append(&mut self, other: Self) -> Result<usize, Overlap<Self>>
+    where
+        V: IntoIterator<Item = A>,
+    {
+        trace_span!("append", values = other.map.len());
+        for theirs in 0..other.map.len() {
+            if let Some(&ours) = other.map.get(theirs).and_then(|a| self.rmap.get(a)) {
+                return Err(Overlap { ours, theirs, integeriser: other });
+            }
+        }
+
+        let offset = self.map.len();
+        self.rmap.reserve(other.map.len());
+        self.rmap.extend(other.rmap.into_iter().map(|(a, k)| (a, k + offset)));
+        for a in other.map {
+            self.map.push(a);
+        }
+        if offset < self.map.len() {
+            self.epoch += 1;
+        }
+        Ok(offset)
+    }
+
+    /// Splits the values into those that satisfy `f` and those that do not,
+    /// each integerised in a new integeriser in the order of their integers.
+    /// Returns both integerisers, each followed by the `Remap` from the
+    /// integers of `self` to its integers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// for w in &["the", "cat", "SAT", "on", "MAT"] {
+    ///     integeriser.integerise(*w);
+    /// }
+    ///
+    /// let (upper, to_upper, lower, to_lower) = integeriser.partition(|w| w.chars().all(char::is_uppercase));
+    ///
+    /// assert_eq!(upper.values(), &vec!["SAT", "MAT"]);
+    /// assert_eq!(lower.values(), &vec!["the", "cat", "on"]);
+    /// assert_eq!(to_upper.apply(&[4, 2]), Ok(vec![1, 0]));
+    /// assert_eq!(to_lower.get(3), Some(2));
+    /// assert_eq!(to_lower.get(4), None);
+    /// ```
+    pub fn partition<F>(&self, mut f: F) -> (Self, Remap, Self, Remap)
+    where
+        A: Clone,
+        S: Clone,
+        V: Default,
+        F: FnMut(&A) -> bool,
+    {
+        trace_span!("partition", values = self.map.len());
+        let empty = || HashIntegeriser { map: V::default(), rmap: HashMap::with_hasher(self.rmap.hasher().clone()), epoch: 0, blocks: Blocks::default() };
+        let (mut yes, mut no) = (empty(), empty());
+        let mut to_yes = Remap { ids: Vec::with_capacity(self.map.len()) };
+        let mut to_no = Remap { ids: Vec::with_capacity(self.map.len()) };
+        for k in 0..self.map.len() {
+            let a = self.map.get(k).expect("ids of an integeriser are dense");
+            if f(a) {
+                to_yes.ids.push(Some(yes.integerise(a.clone())));
+                to_no.ids.push(None);
+            } else {
+                to_yes.ids.push(None);
+                to_no.ids.push(Some(no.integerise(a.clone())));
+            }
+        }
+        (yes, to_yes, no, to_no)
+    }
+}
+
+impl<A: Eq + Hash, S: BuildHasher> HashIntegeriser<A, S, Vec<A>> {
+    /// Moves all values with integers `n` and above into a new integeriser,
+    /// where they are renumbered starting from `0`, and keeps the values with
+    /// integers below `n` in `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the number of values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// for w in &["<s>", "</s>", "cat", "dog"] {
+    ///     integeriser.integerise(*w);
+    /// }
+    ///
+    /// let session = integeriser.split_off(2);
+    ///
+    /// assert_eq!(integeriser.values(), &vec!["<s>", "</s>"]);
+    /// assert_eq!(session.values(), &vec!["cat", "dog"]);
+    /// assert_eq!(integeriser.find_key(&"cat"), None);
+    /// assert_eq!(session.find_key(&"cat"), Some(0));
+    /// ```
+    pub fn split_off(&mut self, n: usize) -> Self
+    where
+        S: Clone,
+    {
+        trace_span!("split_off", at = n);
+        let tail = self.map.split_off(n);
+        if !tail.is_empty() {
+            self.epoch += 1;
+        }
+        let mut rmap = HashMap::with_capacity_and_hasher(tail.len(), self.rmap.hasher().clone());
+        for (k, a) in tail.iter().enumerate() {
+            if let Some((a, _)) = self.rmap.remove_entry(a) {
+                rmap.insert(a, k);
+            }
+        }
+        HashIntegeriser { map: tail, rmap, epoch: 0, blocks: Blocks::default() }
+    }
+
+    /// Replaces the value of the integer `k` by `a` and returns the old value.
+    /// Fails without changing `self` if `k` is unknown or if `a` is already
+    /// integerised as a different integer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser, ReplaceError};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// let k = integeriser.integerise("Cafe");
+    /// integeriser.integerise("bar");
+    ///
+    /// assert_eq!(integeriser.replace_value(k, "café"), Ok("Cafe"));
+    /// assert_eq!(integeriser.find_key(&"café"), Some(k));
+    /// assert_eq!(integeriser.find_key(&"Cafe"), None);
+    ///
+    /// assert_eq!(integeriser.replace_value(k, "bar"), Err(ReplaceError::Duplicate(1)));
+    /// assert_eq!(integeriser.replace_value(2, "baz"), Err(ReplaceError::UnknownId(2)));
+    /// ```
+    pub fn replace_value(&mut self, k: usize, a: A) -> Result<A, ReplaceError>
+    where
+        A: Clone,
+    {
+        if k >= self.map.len() {
+            return Err(ReplaceError::UnknownId(k));
+        }
+        match self.rmap.get(&a) {
+            Some(&j) if j != k => return Err(ReplaceError::Duplicate(j)),
+            Some(_) => return Ok(a),
+            None => (),
+        }
+
+        self.epoch += 1;
+        self.rmap.remove(&self.map[k]);
+        self.rmap.insert(a.clone(), k);
+        Ok(std::mem::replace(&mut self.map[k], a))
+    }
+
+    /// Exchanges the values of the integers `j` and `k`.
+    /// Returns the pairs of old and new integer of the values whose integer
+    /// changed, i.e. `[(j, k), (k, j)]` for `j != k`.
+    /// Fails without changing `self` if `j` or `k` is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser, UnknownId};
+    ///
+    /// let mut integeriser = HashIntegeriser::new();
+    /// for w in &["cat", "dog", "<pad>"] {
+    ///     integeriser.integerise(*w);
+    /// }
+    ///
+    /// assert_eq!(integeriser.swap_ids(2, 0), Ok(vec![(2, 0), (0, 2)]));
+    /// assert_eq!(integeriser.values(), &vec!["<pad>", "dog", "cat"]);
+    /// assert_eq!(integeriser.find_key(&"cat"), Some(2));
+    ///
+    /// assert_eq!(integeriser.swap_ids(1, 1), Ok(vec![]));
+    /// assert_eq!(integeriser.swap_ids(1, 3), Err(UnknownId(3)));
+    /// ```
+    pub fn swap_ids(&mut self, j: usize, k: usize) -> Result<Vec<(usize, usize)>, UnknownId> {
+        for &i in &[j, k] {
+            if i >= self.map.len() {
+                return Err(UnknownId(i));
+            }
+        }
+        if j == k {
+            return Ok(Vec::new());
+        }
+
+        self.epoch += 1;
+        self.map.swap(j, k);
+        for &i in &[j, k] {
+            if let Some(v) = self.rmap.get_mut(&self.map[i]) {
+                *v = i;
+            }
+        }
+        Ok(vec![(j, k), (k, j)])
+    }
 }
 
-impl<'a, A: Clone + Eq + Hash, S: BuildHasher> Integeriser for HashIntegeriser<A, S> {
+impl<A: Clone + Eq + Hash, S: BuildHasher, V: Storage<A>> Integeriser for HashIntegeriser<A, S, V> {
     type Item = A;
 
     fn integerise(&mut self, a: A) -> usize {
@@ -99,6 +831,7 @@ impl<'a, A: Clone + Eq + Hash, S: BuildHasher> Integeriser for HashIntegeriser<A
             hash_map::Entry::Occupied(e) => *e.get(),
             hash_map::Entry::Vacant(e) => {
                 let old_size = self.map.len();
+                self.epoch += 1;
                 self.map.push(e.key().clone());
                 e.insert(old_size);
                 old_size
@@ -119,55 +852,146 @@ impl<'a, A: Clone + Eq + Hash, S: BuildHasher> Integeriser for HashIntegeriser<A
     }
 }
 
-impl<A: Eq + Hash, S: BuildHasher> PartialEq for HashIntegeriser<A, S> {
+impl<A: Eq + Hash, S: BuildHasher, V: PartialEq> PartialEq for HashIntegeriser<A, S, V> {
     fn eq(&self, other: &Self) -> bool {
         self.map == other.map
     }
 }
 
-impl<A: Eq + Hash, S: BuildHasher> Eq for HashIntegeriser<A, S> {}
+impl<A: Eq + Hash, S: BuildHasher, V: Eq> Eq for HashIntegeriser<A, S, V> {}
 
-impl<A: Eq + Hash + PartialOrd, S: BuildHasher> PartialOrd for HashIntegeriser<A, S> {
+impl<A: Eq + Hash, S: BuildHasher, V: PartialOrd> PartialOrd for HashIntegeriser<A, S, V> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.map.partial_cmp(&other.map)
     }
 }
 
-impl<A: Eq + Hash + Ord, S: BuildHasher> Ord for HashIntegeriser<A, S> {
+impl<A: Eq + Hash, S: BuildHasher, V: Ord> Ord for HashIntegeriser<A, S, V> {
     fn cmp(&self, other: &Self) -> Ordering {
         self.map.cmp(&other.map)
     }
 }
 
-impl<A: Eq + Hash, S: BuildHasher> Hash for HashIntegeriser<A, S> {
+impl<A: Eq + Hash, S: BuildHasher, V: Hash> Hash for HashIntegeriser<A, S, V> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.map.hash(state);
     }
 }
 
 #[cfg(feature = "serialisation")]
-impl<A: Eq + Hash + serde::Serialize, BH: BuildHasher> serde::Serialize for HashIntegeriser<A, BH> {
+impl<A: Eq + Hash + serde::Serialize, BH: BuildHasher, V: Storage<A>> serde::Serialize for HashIntegeriser<A, BH, V> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.map.serialize(serializer)
+        serializer.collect_seq((0..self.map.len()).filter_map(|k| self.map.get(k)))
     } 
 }
 
 #[cfg(feature = "serialisation")]
-impl<'de, A: Eq + Hash + Clone + serde::Deserialize<'de>, S: BuildHasher + Default> serde::Deserialize<'de> for HashIntegeriser<A, S> {
+impl<'de, A: Eq + Hash + Clone + serde::Deserialize<'de>, S: BuildHasher + Default, V: Storage<A> + Default> serde::Deserialize<'de> for HashIntegeriser<A, S, V> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let map: Vec<A> = Vec::deserialize(deserializer)?;
-        let rmap: HashMap<A, usize, S> = map.iter().cloned().enumerate().map(| (x,y) | (y,x)).collect();
+        let values: Vec<A> = Vec::deserialize(deserializer)?;
+        let rmap: HashMap<A, usize, S> = values.iter().cloned().enumerate().map(| (x,y) | (y,x)).collect();
+        let mut map = V::default();
+        for a in values {
+            map.push(a);
+        }
 
-        Ok(HashIntegeriser{ map, rmap })
+        Ok(HashIntegeriser{ map, rmap, epoch: 0, blocks: Blocks::default() })
     } 
 }
 
+/// Seed that deserializes an integeriser which uses the given hasher, for
+/// hashers that do not implement `Default`.
+///
+/// # Example
+///
+/// ```
+/// extern crate serde;
+/// extern crate serde_json;
+/// # extern crate integeriser;
+/// use std::collections::hash_map::RandomState;
+/// use serde::de::DeserializeSeed;
+/// use integeriser::{Integeriser, HashIntegeriser, WithHasher};
+///
+/// # fn main() {
+/// let hasher = RandomState::new();
+/// let seed: WithHasher<HashIntegeriser<String>, _> = WithHasher::new(hasher);
+/// let integeriser = seed.deserialize(&mut serde_json::Deserializer::from_str(r#"["a", "b"]"#)).unwrap();
+///
+/// assert_eq!(integeriser.find_key(&"b".to_string()), Some(1));
+///
+/// let json = r#"["c"]"#;
+/// let integeriser: HashIntegeriser<String> =
+///     HashIntegeriser::deserialize_with_hasher(&mut serde_json::Deserializer::from_str(json), RandomState::new()).unwrap();
+/// assert_eq!(integeriser.find_value(0), Some(&"c".to_string()));
+/// # }
+/// ```
+#[cfg(feature = "serialisation")]
+pub struct WithHasher<T, S> {
+    hasher: S,
+    integeriser: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "serialisation")]
+impl<T, S> WithHasher<T, S> {
+    /// Constructs a seed for integerisers that use `hasher`.
+    pub fn new(hasher: S) -> Self {
+        WithHasher { hasher, integeriser: PhantomData }
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<'de, A: Eq + Hash + Clone + serde::Deserialize<'de>, S: BuildHasher, V: Storage<A> + Default> serde::de::DeserializeSeed<'de> for WithHasher<HashIntegeriser<A, S, V>, S> {
+    type Value = HashIntegeriser<A, S, V>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let values: Vec<A> = serde::Deserialize::deserialize(deserializer)?;
+        let mut rmap = HashMap::with_capacity_and_hasher(values.len(), self.hasher);
+        let mut map = V::default();
+        for (k, a) in values.into_iter().enumerate() {
+            rmap.insert(a.clone(), k);
+            map.push(a);
+        }
+
+        Ok(HashIntegeriser{ map, rmap, epoch: 0, blocks: Blocks::default() })
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<A: Eq + Hash + Clone, S: BuildHasher, V: Storage<A> + Default> HashIntegeriser<A, S, V> {
+    /// Deserializes an integeriser that uses `hasher`, see `WithHasher`.
+    pub fn deserialize_with_hasher<'de, D>(deserializer: D, hasher: S) -> Result<Self, D::Error>
+    where
+        A: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        serde::de::DeserializeSeed::deserialize(WithHasher::<Self, S>::new(hasher), deserializer)
+    }
+}
+
 #[cfg(feature = "fnv-hashintegeriser")]
 pub type FnvHashIntegeriser<A> = HashIntegeriser<A, fnv::FnvHasher>;
 
+/// `HashIntegeriser` for strings that stores strings of up to 24 bytes
+/// inline instead of allocating them on the heap.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, CompactHashIntegeriser};
+///
+/// let mut integeriser = CompactHashIntegeriser::new();
+/// let k = integeriser.integerise("token".into());
+///
+/// assert_eq!(integeriser.find_key_borrowed("token"), Some(k));
+/// assert_eq!(integeriser.find_value(k).map(|s| s.as_str()), Some("token"));
+/// ```
+#[cfg(feature = "compact-strings")]
+pub type CompactHashIntegeriser<S=hash_map::RandomState> = HashIntegeriser<compact_str::CompactString, S>;
+
 
 /// Structure that maps to every element of type `A` an integer of type `usize`,
-/// given that `A: Eq + Ord`.  Mapping goes both ways.
+/// given a total order `C: Comparator<A>` on them, by default the natural
+/// order of `A: Ord`.  Mapping goes both ways.
 ///
 /// # Example
 ///
@@ -191,94 +1015,930 @@ pub type FnvHashIntegeriser<A> = HashIntegeriser<A, fnv::FnvHasher>;
 /// assert_ne!(arr1i[2], arr2i[1]);
 /// assert_ne!(arr1i[3], arr2i[3]);
 /// ```
-#[derive(Clone, Debug)]
-pub struct BTreeIntegeriser<A: Ord + Eq> {
+pub struct BTreeIntegeriser<A, C: Comparator<A> = Natural> {
     map: Vec<A>,
-    rmap: BTreeMap<A, usize>,
+    rmap: BTreeMap<Keyed<A, C>, usize>,
+    epoch: u64,
 }
 
-impl<A: Eq + Ord> BTreeIntegeriser<A> {
-    /// Constructs a new, empty `BTreeIntegeriser<A>`.
-    pub fn new() -> BTreeIntegeriser<A> {
-        BTreeIntegeriser {
-            map: Vec::new(),
-            rmap: BTreeMap::new(),
-        }
+/// Order in which a `BTreeIntegeriser` keeps its values, as an alternative
+/// to wrapping the values in a newtype with a custom `Ord` implementation.
+/// The order must be total, and two values must only be equal w.r.t. the
+/// order if they are equal w.r.t. `Eq`.
+///
+/// # Example
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use integeriser::{Integeriser, BTreeIntegeriser, Comparator};
+///
+/// struct ByLength;
+///
+/// impl Comparator<str> for ByLength {
+///     fn compare(a: &str, b: &str) -> Ordering {
+///         a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+///     }
+/// }
+///
+/// impl Comparator<String> for ByLength {
+///     fn compare(a: &String, b: &String) -> Ordering {
+///         <ByLength as Comparator<str>>::compare(a, b)
+///     }
+/// }
+///
+/// let mut integeriser: BTreeIntegeriser<String, ByLength> = BTreeIntegeriser::default();
+/// integeriser.integerise("S -> NP VP".to_string());
+/// integeriser.integerise("NP -> D N".to_string());
+///
+/// assert_eq!(integeriser.find_key_borrowed("NP -> D N"), Some(1));
+/// assert_eq!(integeriser.iter_ordered().map(|(k, _)| k).collect::<Vec<_>>(), vec![1, 0]);
+///
+/// struct Total;
+///
+/// impl Comparator<f64> for Total {
+///     fn compare(a: &f64, b: &f64) -> Ordering {
+///         a.total_cmp(b)
+///     }
+/// }
+///
+/// let mut weights: BTreeIntegeriser<f64, Total> = BTreeIntegeriser::default();
+/// weights.integerise(0.5);
+/// let mut other = weights.clone();
+/// assert!(weights == other);
+/// other.integerise(-1.0);
+/// assert!(weights < other);
+/// ```
+pub trait Comparator<A: ?Sized> {
+    /// Compares `a` with `b`.
+    fn compare(a: &A, b: &A) -> Ordering;
+}
+
+/// The natural order of values `A: Ord`, which `BTreeIntegeriser` uses by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Natural;
+
+impl<A: ?Sized + Ord> Comparator<A> for Natural {
+    fn compare(a: &A, b: &A) -> Ordering {
+        a.cmp(b)
     }
+}
 
-    /// `Vec` containing all the values that have been stored in the iterator.
-    pub fn values(&self) -> &Vec<A> {
-        &self.map
+/// Value ordered by the comparator `C`.
+struct Keyed<A, C> {
+    value: A,
+    order: PhantomData<fn() -> C>,
+}
+
+impl<A, C> Keyed<A, C> {
+    fn new(value: A) -> Self {
+        Keyed { value, order: PhantomData }
     }
 }
 
-impl<A: Eq + Ord + Clone> Integeriser for BTreeIntegeriser<A> {
-    type Item = A;
+impl<A: Clone, C> Clone for Keyed<A, C> {
+    fn clone(&self) -> Self {
+        Keyed::new(self.value.clone())
+    }
+}
 
-    fn integerise(&mut self, a: A) -> usize {
-        match self.rmap.entry(a) {
-            btree_map::Entry::Occupied(e) => *e.get(),
-            btree_map::Entry::Vacant(e) => {
-                let old_size = self.map.len();
-                self.map.push(e.key().clone());
-                e.insert(old_size);
-                old_size
-            }
-        }
+impl<A: fmt::Debug, C> fmt::Debug for Keyed<A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
     }
+}
 
-    fn find_value(&self, k: usize) -> Option<&A> {
-        self.map.get(k)
+impl<A, C: Comparator<A>> PartialEq for Keyed<A, C> {
+    fn eq(&self, other: &Self) -> bool {
+        C::compare(&self.value, &other.value) == Ordering::Equal
     }
+}
 
-    fn find_key(&self, a: &A) -> Option<usize> {
-        self.rmap.get(a).cloned()
+impl<A, C: Comparator<A>> Eq for Keyed<A, C> {}
+
+impl<A, C: Comparator<A>> PartialOrd for Keyed<A, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
+}
 
-    fn size(&self) -> usize {
-        self.map.len()
+impl<A, C: Comparator<A>> Ord for Keyed<A, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        C::compare(&self.value, &other.value)
+    }
+}
+
+/// Borrowed form of a `Keyed<A, C>` for lookups.
+#[repr(transparent)]
+struct Query<Q: ?Sized, C> {
+    order: PhantomData<fn() -> C>,
+    value: Q,
+}
+
+impl<Q: ?Sized, C> Query<Q, C> {
+    fn new(q: &Q) -> &Self {
+        // sound because of `repr(transparent)`
+        unsafe { &*(q as *const Q as *const Query<Q, C>) }
+    }
+}
+
+impl<A: Borrow<Q>, Q: ?Sized, C> Borrow<Query<Q, C>> for Keyed<A, C> {
+    fn borrow(&self) -> &Query<Q, C> {
+        Query::new(self.value.borrow())
     }
 }
 
-impl<A: Eq + Ord> PartialEq for BTreeIntegeriser<A> {
+impl<Q: ?Sized, C: Comparator<Q>> PartialEq for Query<Q, C> {
     fn eq(&self, other: &Self) -> bool {
-        self.map == other.map
+        C::compare(&self.value, &other.value) == Ordering::Equal
     }
 }
 
-impl<A: Eq + Ord> Eq for BTreeIntegeriser<A> {}
+impl<Q: ?Sized, C: Comparator<Q>> Eq for Query<Q, C> {}
 
-impl<A: Eq + Ord> PartialOrd for BTreeIntegeriser<A> {
+impl<Q: ?Sized, C: Comparator<Q>> PartialOrd for Query<Q, C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<A: Eq + Ord> Ord for BTreeIntegeriser<A> {
+impl<Q: ?Sized, C: Comparator<Q>> Ord for Query<Q, C> {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.map.cmp(&other.map)
+        C::compare(&self.value, &other.value)
     }
 }
 
-impl<A: Eq + Ord + Hash> Hash for BTreeIntegeriser<A> {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.map.hash(state);
+impl<A: Clone, C: Comparator<A>> Clone for BTreeIntegeriser<A, C> {
+    fn clone(&self) -> Self {
+        BTreeIntegeriser { map: self.map.clone(), rmap: self.rmap.clone(), epoch: self.epoch }
     }
 }
 
-#[cfg(feature = "serialisation")]
-impl<A: Ord + serde::Serialize> serde::Serialize for BTreeIntegeriser<A> {
-    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        self.map.serialize(serializer)
+impl<A: fmt::Debug, C: Comparator<A>> fmt::Debug for BTreeIntegeriser<A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BTreeIntegeriser")
+            .field("map", &self.map)
+            .field("rmap", &self.rmap)
+            .field("epoch", &self.epoch)
+            .finish()
     }
 }
 
-#[cfg(feature = "serialisation")]
-impl<'de, A: Ord + Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for BTreeIntegeriser<A> {
+impl<A: Ord> BTreeIntegeriser<A> {
+    /// Constructs a new, empty `BTreeIntegeriser<A>`.
+    pub fn new() -> BTreeIntegeriser<A> {
+        BTreeIntegeriser::default()
+    }
+
+    /// Takes `self` apart into the `Vec` of its values and the map from
+    /// every value to its integer.
+    pub fn into_inner(self) -> (Vec<A>, BTreeMap<A, usize>) {
+        let rmap = self.rmap.into_iter().map(|(a, k)| (a.value, k)).collect();
+        (self.map, rmap)
+    }
+
+    /// Reassembles an integeriser from parts as returned by `into_inner`.
+    /// Fails if the map does not map exactly every value of the `Vec` to its index.
+    pub fn from_parts(map: Vec<A>, rmap: BTreeMap<A, usize>) -> Result<Self, InconsistentParts<Vec<A>, BTreeMap<A, usize>>> {
+        let inconsistency = (0..map.len())
+            .find(|&k| rmap.get(&map[k]) != Some(&k))
+            .or((rmap.len() != map.len()).then_some(map.len()));
+        match inconsistency {
+            Some(id) => Err(InconsistentParts { id, values: map, map: rmap }),
+            None => {
+                let rmap = rmap.into_iter().map(|(a, k)| (Keyed::new(a), k)).collect();
+                Ok(BTreeIntegeriser { map, rmap, epoch: 0 })
+            }
+        }
+    }
+}
+
+impl<A, C: Comparator<A>> BTreeIntegeriser<A, C> {
+    /// `Vec` containing all the values that have been stored in the iterator.
+    pub fn values(&self) -> &Vec<A> {
+        &self.map
+    }
+
+    /// Number of values the integeriser can hold without reallocating the
+    /// `Vec` of its values.
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Number of changes to the stored values so far.  Every operation that
+    /// adds or removes values increases the epoch, so comparing it with an
+    /// earlier epoch tells whether data derived from the values is outdated.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Iterates over the integers and values of all values that have been
+    /// integerised after the first `checkpoint` values, e.g. with
+    /// `checkpoint` being an earlier result of `Integeriser::size`.
+    pub fn iter_since<'a>(&'a self, checkpoint: usize) -> impl Iterator<Item = (usize, &'a A)> + 'a {
+        (checkpoint..self.map.len()).filter_map(move |k| self.map.get(k).map(|a| (k, a)))
+    }
+
+    /// Iterates over the integers and values of all values in the order
+    /// given by the comparator `C`.
+    pub fn iter_ordered<'a>(&'a self) -> impl Iterator<Item = (usize, &'a A)> + 'a {
+        self.rmap.iter().map(|(a, &k)| (k, &a.value))
+    }
+
+    /// Lookup the integer that corresponds to a borrowed form of a value,
+    /// e.g. a `&[u8]` for `A = Vec<u8>` or a `&str` for `A = String`.
+    /// With a custom comparator, `C` must order `Q` consistently with `A`.
+    pub fn find_key_borrowed<Q>(&self, q: &Q) -> Option<usize>
+    where
+        A: Borrow<Q>,
+        Q: ?Sized,
+        C: Comparator<Q>,
+    {
+        self.rmap.get(Query::new(q)).cloned()
+    }
+
+    /// Returns the integer and the length (in bytes) of the longest value
+    /// that is a non-empty prefix of `s`.
+    /// Every prefix of `s` is looked up separately; use
+    /// `segment::PrefixIndex` for many queries against a fixed vocabulary.
+    pub fn longest_prefix_match(&self, s: &str) -> Option<(usize, usize)>
+    where
+        A: Borrow<str>,
+        C: Comparator<str>,
+    {
+        s.char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .rev()
+            .filter_map(|len| self.find_key_borrowed(&s[..len]).map(|k| (k, len)))
+            .next()
+    }
+
+    /// Moves all values with integers `n` and above into a new integeriser,
+    /// where they are renumbered starting from `0`, and keeps the values with
+    /// integers below `n` in `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is greater than the number of values.
+    pub fn split_off(&mut self, n: usize) -> Self {
+        trace_span!("split_off", at = n);
+        let tail = self.map.split_off(n);
+        if !tail.is_empty() {
+            self.epoch += 1;
+        }
+        let mut rmap = BTreeMap::new();
+        for (k, a) in tail.iter().enumerate() {
+            if let Some((a, _)) = self.rmap.remove_entry(Query::<A, C>::new(a)) {
+                rmap.insert(a, k);
+            }
+        }
+        BTreeIntegeriser { map: tail, rmap, epoch: 0 }
+    }
+
+    /// Replaces the value of the integer `k` by `a` and returns the old value.
+    /// Fails without changing `self` if `k` is unknown or if `a` is already
+    /// integerised as a different integer.
+    pub fn replace_value(&mut self, k: usize, a: A) -> Result<A, ReplaceError>
+    where
+        A: Clone,
+    {
+        if k >= self.map.len() {
+            return Err(ReplaceError::UnknownId(k));
+        }
+        match self.rmap.get(Query::<A, C>::new(&a)) {
+            Some(&j) if j != k => return Err(ReplaceError::Duplicate(j)),
+            Some(_) => return Ok(a),
+            None => (),
+        }
+
+        self.epoch += 1;
+        self.rmap.remove(Query::<A, C>::new(&self.map[k]));
+        self.rmap.insert(Keyed::new(a.clone()), k);
+        Ok(std::mem::replace(&mut self.map[k], a))
+    }
+
+    /// Exchanges the values of the integers `j` and `k`.
+    /// Returns the pairs of old and new integer of the values whose integer
+    /// changed, i.e. `[(j, k), (k, j)]` for `j != k`.
+    /// Fails without changing `self` if `j` or `k` is unknown.
+    pub fn swap_ids(&mut self, j: usize, k: usize) -> Result<Vec<(usize, usize)>, UnknownId> {
+        for &i in &[j, k] {
+            if i >= self.map.len() {
+                return Err(UnknownId(i));
+            }
+        }
+        if j == k {
+            return Ok(Vec::new());
+        }
+
+        self.epoch += 1;
+        self.map.swap(j, k);
+        for &i in &[j, k] {
+            if let Some(v) = self.rmap.get_mut(Query::<A, C>::new(&self.map[i])) {
+                *v = i;
+            }
+        }
+        Ok(vec![(j, k), (k, j)])
+    }
+
+    /// Moves all values of `other` into `self`, where every value keeps its
+    /// integer shifted by the number of values in `self`, and returns that
+    /// shift.  Fails without changing `self` if both share a value.
+    pub fn append(&mut self, other: Self) -> Result<usize, Overlap<Self>> {
+        trace_span!("append", values = other.map.len());
+        for (theirs, a) in other.map.iter().enumerate() {
+            if let Some(&ours) = self.rmap.get(Query::<A, C>::new(a)) {
+                return Err(Overlap { ours, theirs, integeriser: other });
+            }
+        }
+
+        let offset = self.map.len();
+        self.rmap.extend(other.rmap.into_iter().map(|(a, k)| (a, k + offset)));
+        self.map.extend(other.map);
+        if offset < self.map.len() {
+            self.epoch += 1;
+        }
+        Ok(offset)
+    }
+
+    /// Splits the values into those that satisfy `f` and those that do not,
+    /// each integerised in a new integeriser in the order of their integers.
+    /// Returns both integerisers, each followed by the `Remap` from the
+    /// integers of `self` to its integers.
+    pub fn partition<F>(&self, mut f: F) -> (Self, Remap, Self, Remap)
+    where
+        A: Clone,
+        F: FnMut(&A) -> bool,
+    {
+        trace_span!("partition", values = self.map.len());
+        let (mut yes, mut no) = (Self::default(), Self::default());
+        let mut to_yes = Remap { ids: Vec::with_capacity(self.map.len()) };
+        let mut to_no = Remap { ids: Vec::with_capacity(self.map.len()) };
+        for a in &self.map {
+            if f(a) {
+                to_yes.ids.push(Some(yes.integerise(a.clone())));
+                to_no.ids.push(None);
+            } else {
+                to_yes.ids.push(None);
+                to_no.ids.push(Some(no.integerise(a.clone())));
+            }
+        }
+        (yes, to_yes, no, to_no)
+    }
+}
+
+/// `BTreeIntegeriser` for strings that stores strings of up to 24 bytes
+/// inline instead of allocating them on the heap.
+#[cfg(feature = "compact-strings")]
+pub type CompactBTreeIntegeriser = BTreeIntegeriser<compact_str::CompactString>;
+
+impl<A, C: Comparator<A>> Default for BTreeIntegeriser<A, C> {
+    fn default() -> Self {
+        BTreeIntegeriser {
+            map: Vec::new(),
+            rmap: BTreeMap::new(),
+            epoch: 0,
+        }
+    }
+}
+
+impl<A: Clone, C: Comparator<A>> Integeriser for BTreeIntegeriser<A, C> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        match self.rmap.entry(Keyed::new(a)) {
+            btree_map::Entry::Occupied(e) => *e.get(),
+            btree_map::Entry::Vacant(e) => {
+                let old_size = self.map.len();
+                self.epoch += 1;
+                self.map.push(e.key().value.clone());
+                e.insert(old_size);
+                old_size
+            }
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        self.map.get(k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(Query::<A, C>::new(a)).cloned()
+    }
+
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+}
+
+impl<A: PartialEq, C: Comparator<A>> PartialEq for BTreeIntegeriser<A, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<A: Eq, C: Comparator<A>> Eq for BTreeIntegeriser<A, C> {}
+
+impl<A: PartialOrd, C: Comparator<A>> PartialOrd for BTreeIntegeriser<A, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.map.partial_cmp(&other.map)
+    }
+}
+
+impl<A: Ord, C: Comparator<A>> Ord for BTreeIntegeriser<A, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.map.cmp(&other.map)
+    }
+}
+
+impl<A: Hash, C: Comparator<A>> Hash for BTreeIntegeriser<A, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<A: serde::Serialize, C: Comparator<A>> serde::Serialize for BTreeIntegeriser<A, C> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.map.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<'de, A: Clone + serde::Deserialize<'de>, C: Comparator<A>> serde::Deserialize<'de> for BTreeIntegeriser<A, C> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map: Vec<A> = Vec::deserialize(deserializer)?;
+        let rmap: BTreeMap<Keyed<A, C>, usize> = map.iter().cloned().enumerate().map(|(x,y)| (Keyed::new(y),x)).collect();
+
+        Ok(BTreeIntegeriser{ map, rmap, epoch: 0 })
+    }
+}
+
+
+/// Structure that maps to every element of type `A` an integer of type `usize`,
+/// given that `A: Eq + Hash`.  Mapping goes both ways.
+/// Values and integers are kept in a single `indexmap::IndexSet`, which also
+/// allows removing values.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, IndexMapIntegeriser};
+///
+/// let mut integeriser = IndexMapIntegeriser::new();
+///
+/// let a = integeriser.integerise("a");
+/// let b = integeriser.integerise("b");
+/// let c = integeriser.integerise("c");
+///
+/// assert_eq!(integeriser.integerise("b"), b);
+///
+/// // the last value takes over the integer of the removed value
+/// assert_eq!(integeriser.swap_remove(&"a"), Some(a));
+/// assert_eq!(integeriser.find_key(&"c"), Some(a));
+/// assert_eq!(integeriser.find_value(c), None);
+/// assert_eq!(integeriser.size(), 2);
+/// ```
+#[cfg(feature = "indexmap-integeriser")]
+#[derive(Clone, Debug)]
+pub struct IndexMapIntegeriser<A, S=hash_map::RandomState>
+where
+    A: Eq + Hash,
+    S: BuildHasher
+{
+    set: indexmap::IndexSet<A, S>,
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash> IndexMapIntegeriser<A, hash_map::RandomState> {
+    /// Constructs a new, empty `IndexMapIntegeriser<A>`.
+    pub fn new() -> Self {
+        IndexMapIntegeriser {
+            set: indexmap::IndexSet::new(),
+        }
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher + Default> Default for IndexMapIntegeriser<A, S> {
+    fn default() -> Self {
+        IndexMapIntegeriser {
+            set: indexmap::IndexSet::default(),
+        }
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher> IndexMapIntegeriser<A, S> {
+    /// `IndexSet` containing all the values that have been stored in the iterator.
+    pub fn values(&self) -> &indexmap::IndexSet<A, S> {
+        &self.set
+    }
+
+    /// Number of values the integeriser can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.set.capacity()
+    }
+
+    /// Reference to the `BuildHasher` of the `IndexSet`.
+    pub fn hasher(&self) -> &S {
+        self.set.hasher()
+    }
+
+    /// Removes the value `a` and returns its former integer.
+    /// The value with the largest integer is assigned the integer of `a`,
+    /// so that the integers stay consecutive.
+    pub fn swap_remove(&mut self, a: &A) -> Option<usize> {
+        self.set.swap_remove_full(a).map(|(k, _)| k)
+    }
+
+    /// Removes the value with integer `k` and returns it.
+    /// The value with the largest integer is assigned the integer `k`,
+    /// so that the integers stay consecutive.
+    pub fn swap_remove_index(&mut self, k: usize) -> Option<A> {
+        self.set.swap_remove_index(k)
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher> Integeriser for IndexMapIntegeriser<A, S> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        self.set.insert_full(a).0
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        self.set.get_index(k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        self.set.get_index_of(a)
+    }
+
+    fn size(&self) -> usize {
+        self.set.len()
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher> PartialEq for IndexMapIntegeriser<A, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.set.iter().eq(other.set.iter())
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher> Eq for IndexMapIntegeriser<A, S> {}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash + PartialOrd, S: BuildHasher> PartialOrd for IndexMapIntegeriser<A, S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.set.iter().partial_cmp(other.set.iter())
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash + Ord, S: BuildHasher> Ord for IndexMapIntegeriser<A, S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.set.iter().cmp(other.set.iter())
+    }
+}
+
+#[cfg(feature = "indexmap-integeriser")]
+impl<A: Eq + Hash, S: BuildHasher> Hash for IndexMapIntegeriser<A, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.set.as_slice().hash(state);
+    }
+}
+
+#[cfg(all(feature = "indexmap-integeriser", feature = "serialisation"))]
+impl<A: Eq + Hash + serde::Serialize, BH: BuildHasher> serde::Serialize for IndexMapIntegeriser<A, BH> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.set.iter())
+    }
+}
+
+#[cfg(all(feature = "indexmap-integeriser", feature = "serialisation"))]
+impl<'de, A: Eq + Hash + serde::Deserialize<'de>, S: BuildHasher + Default> serde::Deserialize<'de> for IndexMapIntegeriser<A, S> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map: Vec<A> = Vec::deserialize(deserializer)?;
+
+        Ok(IndexMapIntegeriser{ set: map.into_iter().collect() })
+    }
+}
+
+#[cfg(all(feature = "indexmap-integeriser", feature = "serialisation"))]
+impl<'de, A: Eq + Hash + serde::Deserialize<'de>, S: BuildHasher> serde::de::DeserializeSeed<'de> for WithHasher<IndexMapIntegeriser<A, S>, S> {
+    type Value = IndexMapIntegeriser<A, S>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let map: Vec<A> = serde::Deserialize::deserialize(deserializer)?;
+        let mut set = indexmap::IndexSet::with_capacity_and_hasher(map.len(), self.hasher);
+        set.extend(map);
+
+        Ok(IndexMapIntegeriser{ set })
+    }
+}
+
+#[cfg(all(feature = "indexmap-integeriser", feature = "serialisation"))]
+impl<A: Eq + Hash, S: BuildHasher> IndexMapIntegeriser<A, S> {
+    /// Deserializes an integeriser that uses `hasher`, see `WithHasher`.
+    pub fn deserialize_with_hasher<'de, D>(deserializer: D, hasher: S) -> Result<Self, D::Error>
+    where
+        A: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        serde::de::DeserializeSeed::deserialize(WithHasher::<Self, S>::new(hasher), deserializer)
+    }
+}
+
+
+/// Structure that maps to at most `N` elements of type `A` an integer of type
+/// `usize`, given that `A: Eq + Hash`.  Mapping goes both ways.
+/// All values are stored inline in fixed-capacity `heapless` containers, so
+/// the structure never allocates.  `N` must be a power of two greater than one.
+///
+/// Use `try_integerise` to handle a full integeriser; `Integeriser::integerise`
+/// panics instead.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HeaplessIntegeriser};
+///
+/// let mut integeriser: HeaplessIntegeriser<&str, 2> = HeaplessIntegeriser::new();
+///
+/// assert_eq!(integeriser.try_integerise("if"), Ok(0));
+/// assert_eq!(integeriser.try_integerise("then"), Ok(1));
+/// assert_eq!(integeriser.try_integerise("if"), Ok(0));
+///
+/// let error = integeriser.try_integerise("else").unwrap_err();
+/// assert_eq!(error.0, "else");
+/// assert_eq!(integeriser.size(), 2);
+/// ```
+#[cfg(feature = "heapless-integeriser")]
+#[derive(Clone, Debug)]
+pub struct HeaplessIntegeriser<A: Eq + Hash, const N: usize> {
+    map: heapless::Vec<A, N>,
+    rmap: heapless::FnvIndexMap<A, usize, N>,
+}
+
+/// Error for a value that could not be integerised because the integeriser
+/// is full.  Contains the rejected value.
+#[cfg(feature = "heapless-integeriser")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CapacityError<A>(pub A);
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A> fmt::Display for CapacityError<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the integeriser is full")
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: fmt::Debug> Error for CapacityError<A> {}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash, const N: usize> HeaplessIntegeriser<A, N> {
+    /// Constructs a new, empty `HeaplessIntegeriser<A, N>`.
+    pub fn new() -> Self {
+        HeaplessIntegeriser {
+            map: heapless::Vec::new(),
+            rmap: heapless::FnvIndexMap::new(),
+        }
+    }
+
+    /// `Vec` containing all the values that have been stored in the iterator.
+    pub fn values(&self) -> &heapless::Vec<A, N> {
+        &self.map
+    }
+
+    /// Number of values the integeriser can hold, i.e. `N`.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash, const N: usize> Default for HeaplessIntegeriser<A, N> {
+    fn default() -> Self {
+        HeaplessIntegeriser::new()
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Clone + Eq + Hash, const N: usize> HeaplessIntegeriser<A, N> {
+    /// Returns a unique integer for the given value `a`, see
+    /// `Integeriser::integerise`, or `a` itself if `a` is new and the
+    /// integeriser already holds `N` values.
+    pub fn try_integerise(&mut self, a: A) -> Result<usize, CapacityError<A>> {
+        if let Some(&k) = self.rmap.get(&a) {
+            return Ok(k);
+        }
+        if self.map.is_full() {
+            count!("integeriser_rejections_total", 1);
+            return Err(CapacityError(a));
+        }
+
+        let old_size = self.map.len();
+        // both containers have room for `N` values
+        let _ = self.map.push(a.clone());
+        let _ = self.rmap.insert(a, old_size);
+        Ok(old_size)
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Clone + Eq + Hash, const N: usize> Integeriser for HeaplessIntegeriser<A, N> {
+    type Item = A;
+
+    /// # Panics
+    ///
+    /// Panics if `a` is new and the integeriser already holds `N` values.
+    fn integerise(&mut self, a: A) -> usize {
+        match self.try_integerise(a) {
+            Ok(k) => k,
+            Err(_) => panic!("HeaplessIntegeriser is full (capacity {})", N),
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        self.map.get(k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(a).cloned()
+    }
+
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash, const N: usize> PartialEq for HeaplessIntegeriser<A, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash, const N: usize> Eq for HeaplessIntegeriser<A, N> {}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash + PartialOrd, const N: usize> PartialOrd for HeaplessIntegeriser<A, N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.map.partial_cmp(&other.map)
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash + Ord, const N: usize> Ord for HeaplessIntegeriser<A, N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.map.cmp(&other.map)
+    }
+}
+
+#[cfg(feature = "heapless-integeriser")]
+impl<A: Eq + Hash, const N: usize> Hash for HeaplessIntegeriser<A, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+
+/// Structure that maps to every element of type `A` an integer of type `usize`,
+/// given that `A: Eq + Hash`.  Mapping goes both ways.
+/// Values are kept in persistent data structures of the `im` crate, so that
+/// cloning is `O(1)` and versions share unchanged parts.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, PersistentIntegeriser};
+///
+/// let base = PersistentIntegeriser::new().with("S").0.with("NP").0;
+///
+/// let (left, k) = base.with("VP");
+/// let (right, l) = base.with("PP");
+///
+/// assert_eq!(k, 2);
+/// assert_eq!(l, 2);
+/// assert_eq!(left.find_value(2), Some(&"VP"));
+/// assert_eq!(right.find_value(2), Some(&"PP"));
+/// assert_eq!(base.size(), 2);
+/// ```
+#[cfg(feature = "persistent-integeriser")]
+#[derive(Clone, Debug)]
+pub struct PersistentIntegeriser<A: Clone + Eq + Hash> {
+    map: im::Vector<A>,
+    rmap: im::HashMap<A, usize>,
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> PersistentIntegeriser<A> {
+    /// Constructs a new, empty `PersistentIntegeriser<A>`.
+    pub fn new() -> Self {
+        PersistentIntegeriser {
+            map: im::Vector::new(),
+            rmap: im::HashMap::new(),
+        }
+    }
+
+    /// `Vector` containing all the values that have been stored in the iterator.
+    pub fn values(&self) -> &im::Vector<A> {
+        &self.map
+    }
+
+    /// Returns a new version of the integeriser that additionally contains `a`,
+    /// together with the integer of `a`.  `self` is left unchanged.
+    /// Takes `O(log n)` time and shares structure with `self`.
+    pub fn with(&self, a: A) -> (Self, usize) {
+        let mut next = self.clone();
+        let k = next.integerise(a);
+        (next, k)
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> Default for PersistentIntegeriser<A> {
+    fn default() -> Self {
+        PersistentIntegeriser::new()
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> Integeriser for PersistentIntegeriser<A> {
+    type Item = A;
+
+    fn integerise(&mut self, a: A) -> usize {
+        if let Some(&k) = self.rmap.get(&a) {
+            return k;
+        }
+        let old_size = self.map.len();
+        self.map.push_back(a.clone());
+        self.rmap.insert(a, old_size);
+        old_size
+    }
+
+    fn find_value(&self, k: usize) -> Option<&A> {
+        self.map.get(k)
+    }
+
+    fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(a).cloned()
+    }
+
+    fn size(&self) -> usize {
+        self.map.len()
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> PartialEq for PersistentIntegeriser<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> Eq for PersistentIntegeriser<A> {}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash + PartialOrd> PartialOrd for PersistentIntegeriser<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.map.iter().partial_cmp(other.map.iter())
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash + Ord> Ord for PersistentIntegeriser<A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.map.cmp(&other.map)
+    }
+}
+
+#[cfg(feature = "persistent-integeriser")]
+impl<A: Clone + Eq + Hash> Hash for PersistentIntegeriser<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state);
+    }
+}
+
+#[cfg(all(feature = "persistent-integeriser", feature = "serialisation"))]
+impl<A: Clone + Eq + Hash + serde::Serialize> serde::Serialize for PersistentIntegeriser<A> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.map.iter())
+    }
+}
+
+#[cfg(all(feature = "persistent-integeriser", feature = "serialisation"))]
+impl<'de, A: Clone + Eq + Hash + serde::Deserialize<'de>> serde::Deserialize<'de> for PersistentIntegeriser<A> {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let map: Vec<A> = Vec::deserialize(deserializer)?;
-        let rmap: BTreeMap<A, usize> = map.iter().cloned().enumerate().map(|(x,y)| (y,x)).collect();
+        let rmap: im::HashMap<A, usize> = map.iter().cloned().enumerate().map(|(x,y)| (y,x)).collect();
 
-        Ok(BTreeIntegeriser{ map, rmap })
+        Ok(PersistentIntegeriser{ map: map.into_iter().collect(), rmap })
     }
 }
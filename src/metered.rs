@@ -0,0 +1,87 @@
+//! Recording the use of an integeriser with `metrics` counters.
+//!
+//! Besides the counters of `Metered`, the bounded integerisers count the
+//! values they reject (`integeriser_rejections_total`) and
+//! `weak::WeakIntegeriser` counts the values it collects
+//! (`integeriser_evictions_total`).
+
+use metrics::{counter, Counter};
+
+use Integeriser;
+
+/// Integeriser that counts the new values (`integeriser_inserts_total`) and
+/// the lookups of values that are known (`integeriser_lookup_hits_total`)
+/// or unknown (`integeriser_lookup_misses_total`) to an inner integeriser.
+/// Both `integerise` and `find_key` count as lookups.
+/// Every counter has the label `integeriser` with the name of the wrapper.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::metered::Metered;
+///
+/// let mut vocab = Metered::new(HashIntegeriser::new(), "vocab");
+/// vocab.integerise("a");
+/// vocab.integerise("a");
+///
+/// assert_eq!(vocab.find_key(&"b"), None);
+/// assert_eq!(vocab.into_inner().size(), 1);
+/// ```
+#[derive(Debug)]
+pub struct Metered<I> {
+    inner: I,
+    inserts: Counter,
+    hits: Counter,
+    misses: Counter,
+}
+
+impl<I: Integeriser> Metered<I> {
+    /// Wraps `inner`, registering its counters under the label `integeriser = name`.
+    pub fn new(inner: I, name: &'static str) -> Self {
+        Metered {
+            inner,
+            inserts: counter!("integeriser_inserts_total", "integeriser" => name),
+            hits: counter!("integeriser_lookup_hits_total", "integeriser" => name),
+            misses: counter!("integeriser_lookup_misses_total", "integeriser" => name),
+        }
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Integeriser> Integeriser for Metered<I> {
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let size = self.inner.size();
+        let k = self.inner.integerise(a);
+        if self.inner.size() > size {
+            self.misses.increment(1);
+            self.inserts.increment(1);
+        } else {
+            self.hits.increment(1);
+        }
+        k
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        let k = self.inner.find_key(a);
+        match k {
+            Some(_) => self.hits.increment(1),
+            None => self.misses.increment(1),
+        }
+        k
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
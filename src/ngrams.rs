@@ -0,0 +1,118 @@
+//! Counting the n-grams of streams of integers, e.g. of the integers of the
+//! tokens of a text.
+
+use {HashIntegeriser, Integeriser};
+
+/// Largest `n` for which n-grams can be counted.
+pub const MAX_N: usize = 5;
+
+/// Counts of the n-grams, for a fixed `n`, of one or more streams of integers.
+/// The n-grams themselves are integerised, so every distinct n-gram is
+/// stored once, together with its count.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::ngrams::NgramCounts;
+///
+/// let mut bigrams = NgramCounts::new(2);
+/// bigrams.count(vec![0, 1, 0, 1, 2]);
+///
+/// let mut more = NgramCounts::new(2);
+/// more.count(vec![1, 2]);
+/// bigrams.merge(more);
+///
+/// assert_eq!(bigrams.get(&[0, 1]), 2);
+/// assert_eq!(bigrams.get(&[2, 0]), 0);
+/// assert_eq!(bigrams.top_k(2), vec![(&[0, 1][..], 2), (&[1, 2][..], 2)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NgramCounts {
+    n: usize,
+    ngrams: HashIntegeriser<Vec<usize>>,
+    counts: Vec<u64>,
+}
+
+impl NgramCounts {
+    /// Constructs empty counts of n-grams of length `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is `0` or greater than `MAX_N`.
+    pub fn new(n: usize) -> Self {
+        assert!(n > 0 && n <= MAX_N, "n-grams must have a length between 1 and {}", MAX_N);
+        NgramCounts { n, ngrams: HashIntegeriser::new(), counts: Vec::new() }
+    }
+
+    /// The length of the counted n-grams.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// The integeriser of the distinct n-grams, whose integers are the
+    /// indices of their counts.
+    pub fn ngrams(&self) -> &HashIntegeriser<Vec<usize>> {
+        &self.ngrams
+    }
+
+    /// Counts all n-grams in `ids`.  N-grams do not span several calls.
+    pub fn count<T: IntoIterator<Item = usize>>(&mut self, ids: T) {
+        let mut window = [0; MAX_N];
+        let mut filled = 0;
+        for k in ids {
+            if filled < self.n {
+                window[filled] = k;
+                filled += 1;
+            } else {
+                window.copy_within(1..self.n, 0);
+                window[self.n - 1] = k;
+            }
+            if filled == self.n {
+                self.add(&window[..self.n], 1);
+            }
+        }
+    }
+
+    fn add(&mut self, ngram: &[usize], count: u64) {
+        let k = match self.ngrams.find_key_borrowed(ngram) {
+            Some(k) => k,
+            None => {
+                self.counts.push(0);
+                self.ngrams.integerise(ngram.to_vec())
+            }
+        };
+        self.counts[k] += count;
+    }
+
+    /// The number of times `ngram` has been counted.
+    pub fn get(&self, ngram: &[usize]) -> u64 {
+        self.ngrams.find_key_borrowed(ngram).map_or(0, |k| self.counts[k])
+    }
+
+    /// Adds the counts of `other` to `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` counts n-grams of a different length.
+    pub fn merge(&mut self, other: NgramCounts) {
+        assert_eq!(self.n, other.n, "only counts of n-grams of the same length can be merged");
+        for (ngram, count) in other.ngrams.values().iter().zip(other.counts) {
+            self.add(ngram, count);
+        }
+    }
+
+    /// The `k` most frequent n-grams with their counts, in descending order
+    /// of counts.  N-grams with equal counts are ordered by their first occurrence.
+    pub fn top_k(&self, k: usize) -> Vec<(&[usize], u64)> {
+        let mut ids: Vec<usize> = (0..self.counts.len()).collect();
+        let by_count = |&i: &usize, &j: &usize| self.counts[j].cmp(&self.counts[i]).then(i.cmp(&j));
+        if k < ids.len() {
+            ids.select_nth_unstable_by(k, by_count);
+            ids.truncate(k);
+        }
+        ids.sort_unstable_by(by_count);
+        ids.into_iter()
+            .map(|i| (&self.ngrams.values()[i][..], self.counts[i]))
+            .collect()
+    }
+}
@@ -0,0 +1,131 @@
+//! Unicode normalisation of string values before they are integerised.
+
+use unicode_normalization::UnicodeNormalization;
+
+use Integeriser;
+
+/// Unicode normalisation forms, see
+/// [UAX #15](https://www.unicode.org/reports/tr15/).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Form {
+    /// Canonical composition: canonically equivalent strings, e.g. `é` as one
+    /// code point or as `e` with a combining accent, are composed into the
+    /// same code points where possible.
+    Nfc,
+    /// Canonical decomposition: canonically equivalent strings are
+    /// decomposed into the same code points, e.g. `é` into `e` followed by a
+    /// combining accent.
+    Nfd,
+    /// Compatibility composition: like `Nfc`, but compatibility variants,
+    /// e.g. the ligature `ﬁ` or the superscript `²`, are replaced by their
+    /// plain forms (`fi`, `2`) first.
+    Nfkc,
+    /// Compatibility decomposition: like `Nfd`, but compatibility variants
+    /// are replaced by their plain forms as well.
+    Nfkd,
+}
+
+/// Configuration of the normalisation that is applied to every string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Normalisation {
+    /// Normalisation form, or `None` to keep the code points as they are.
+    pub form: Option<Form>,
+    /// Whether every character is mapped to lowercase (using `str::to_lowercase`)
+    /// before the string is normalised.
+    pub case_fold: bool,
+}
+
+impl Default for Normalisation {
+    /// NFC without case folding.
+    fn default() -> Self {
+        Normalisation { form: Some(Form::Nfc), case_fold: false }
+    }
+}
+
+impl Normalisation {
+    /// Applies the normalisation to `s`.
+    pub fn normalise(&self, s: &str) -> String {
+        let folded;
+        let s = if self.case_fold {
+            folded = s.to_lowercase();
+            &folded
+        } else {
+            s
+        };
+        match self.form {
+            Some(Form::Nfc) => s.nfc().collect(),
+            Some(Form::Nfd) => s.nfd().collect(),
+            Some(Form::Nfkc) => s.nfkc().collect(),
+            Some(Form::Nfkd) => s.nfkd().collect(),
+            None => s.to_string(),
+        }
+    }
+}
+
+/// Integeriser that normalises every string before passing it on to an
+/// inner integeriser, so that only normalised strings are stored.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::normalise::{Form, Normalisation, NormalisingIntegeriser};
+///
+/// let normalisation = Normalisation { form: Some(Form::Nfc), case_fold: true };
+/// let mut integeriser = NormalisingIntegeriser::new(HashIntegeriser::new(), normalisation);
+///
+/// let composed = integeriser.integerise("\u{c5}ngstr\u{f6}m".to_string());
+/// let decomposed = integeriser.integerise("A\u{30a}ngstro\u{308}m".to_string());
+///
+/// assert_eq!(composed, decomposed);
+/// assert_eq!(integeriser.find_key(&"\u{e5}ngstr\u{f6}m".to_string()), Some(composed));
+/// assert_eq!(integeriser.find_value(composed), Some(&"\u{e5}ngstr\u{f6}m".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NormalisingIntegeriser<I> {
+    inner: I,
+    normalisation: Normalisation,
+}
+
+impl<I: Integeriser<Item = String>> NormalisingIntegeriser<I> {
+    /// Wraps `inner`, which should not contain any unnormalised strings yet.
+    pub fn new(inner: I, normalisation: Normalisation) -> Self {
+        NormalisingIntegeriser { inner, normalisation }
+    }
+
+    /// The normalisation that is applied to every string.
+    pub fn normalisation(&self) -> &Normalisation {
+        &self.normalisation
+    }
+
+    /// The inner integeriser.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Integeriser<Item = String>> Integeriser for NormalisingIntegeriser<I> {
+    type Item = String;
+
+    fn integerise(&mut self, a: String) -> usize {
+        let a = self.normalisation.normalise(&a);
+        self.inner.integerise(a)
+    }
+
+    fn find_value(&self, k: usize) -> Option<&String> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &String) -> Option<usize> {
+        self.inner.find_key(&self.normalisation.normalise(a))
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
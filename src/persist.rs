@@ -0,0 +1,671 @@
+//! Persisting integerisers as append-only journals.
+//!
+//! A journal contains one JSON-encoded value per line, in the order of
+//! their integers.  Since integers are assigned consecutively, a journal
+//! can be extended by the values that were added since it was last written.
+//! Every batch of values written at once is followed by a line
+//! `#crc32 <checksum> <number of values>` over the lines of the batch and
+//! by an end record `#end <number of all values so far>`.  Both are verified
+//! on load, and the journal must end with an end record, so that truncated
+//! or damaged journals are rejected.  A journal that is cut right after the
+//! end record of a batch is indistinguishable from the journal before the
+//! next batch was appended.
+//!
+//! Complete journals can be saved to and loaded from files with
+//! `save_to_path` and `load_from_path`, optionally compressed with gzip
+//! (feature `gzip`) or Zstandard (feature `zstd-compression`).
+//! Files are saved via a temporary file that replaces the target only once
+//! it is complete.  Uncompressed files can be appended to with
+//! `Journal::flush_new`.
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use portable::{self, Overflow};
+use Integeriser;
+
+/// Errors that can occur while writing or replaying a journal.
+#[derive(Debug)]
+pub enum JournalError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// A line of the journal could not be encoded or decoded.
+    Json(serde_json::Error),
+    /// The value with the given position in the journal repeats an earlier value.
+    Duplicate(usize),
+    /// The integeriser has fewer values than have already been written.
+    Truncated,
+    /// The integeriser has no value for the given integer below its size.
+    MissingId(usize),
+    /// The checksum line of a batch or the end record of the journal is
+    /// missing, malformed, or does not match the journal.
+    CorruptFile(&'static str),
+    /// A number of values in the file does not fit into `usize` on this
+    /// machine.
+    Overflow(Overflow),
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JournalError::Io(ref e) => write!(f, "i/o error: {}", e),
+            JournalError::Json(ref e) => write!(f, "malformed journal entry: {}", e),
+            JournalError::Duplicate(line) => write!(f, "value {} of the journal repeats an earlier value", line),
+            JournalError::Truncated => write!(f, "the integeriser has fewer values than the journal"),
+            JournalError::MissingId(k) => write!(f, "the integeriser has no value for the integer {}", k),
+            JournalError::CorruptFile(reason) => write!(f, "corrupt file: {}", reason),
+            JournalError::Overflow(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for JournalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            JournalError::Io(ref e) => Some(e),
+            JournalError::Json(ref e) => Some(e),
+            JournalError::Overflow(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for JournalError {
+    fn from(e: io::Error) -> Self {
+        JournalError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for JournalError {
+    fn from(e: serde_json::Error) -> Self {
+        JournalError::Json(e)
+    }
+}
+
+impl From<Overflow> for JournalError {
+    fn from(e: Overflow) -> Self {
+        JournalError::Overflow(e)
+    }
+}
+
+/// Keeps track of how many values of an integeriser have been written to
+/// its journal, so that only new values are written by `flush_new`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::persist::{replay, Journal};
+///
+/// let mut integeriser = HashIntegeriser::new();
+/// let mut journal = Journal::new();
+/// let mut log = Vec::new();
+///
+/// integeriser.integerise("a".to_string());
+/// integeriser.integerise("b".to_string());
+/// assert_eq!(journal.flush_new(&integeriser, &mut log).unwrap(), 2);
+///
+/// integeriser.integerise("c".to_string());
+/// assert_eq!(journal.pending(&integeriser), 1);
+/// assert_eq!(journal.flush_new(&integeriser, &mut log).unwrap(), 1);
+///
+/// let (restored, journal): (HashIntegeriser<String>, _) = replay(&log[..]).unwrap();
+/// assert_eq!(restored, integeriser);
+/// assert_eq!(journal.flushed(), 3);
+///
+/// assert!(replay::<HashIntegeriser<String>, _>(&log[..log.len() - 1]).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Journal {
+    flushed: usize,
+}
+
+impl Journal {
+    /// Constructs a tracker for an empty journal.
+    pub fn new() -> Self {
+        Journal { flushed: 0 }
+    }
+
+    /// Constructs a tracker for a journal that already contains the values
+    /// with integers below `flushed`.
+    pub fn resume(flushed: usize) -> Self {
+        Journal { flushed }
+    }
+
+    /// Number of values that have been written to the journal.
+    pub fn flushed(&self) -> usize {
+        self.flushed
+    }
+
+    /// Number of values of `integeriser` that have not been written yet.
+    pub fn pending<I: Integeriser>(&self, integeriser: &I) -> usize {
+        integeriser.size().saturating_sub(self.flushed)
+    }
+
+    /// Appends all values of `integeriser` that have not been written yet to
+    /// `writer`, followed by their checksum line and an end record, and
+    /// returns their number.
+    /// Nothing but the end record of an empty journal is written if there
+    /// are no such values.
+    /// Fails if an integer below the size of `integeriser` has no value.
+    pub fn flush_new<I, W>(&mut self, integeriser: &I, writer: W) -> Result<usize, JournalError>
+    where
+        I: Integeriser,
+        I::Item: Serialize,
+        W: Write,
+    {
+        if integeriser.size() < self.flushed {
+            return Err(JournalError::Truncated);
+        }
+
+        let fresh = integeriser.size() - self.flushed;
+        if fresh == 0 {
+            if self.flushed == 0 {
+                let mut writer = writer;
+                writeln!(writer, "#end 0")?;
+                writer.flush()?;
+            }
+            return Ok(0);
+        }
+        let mut writer = Crc32Writer { inner: writer, crc: Crc32::new() };
+        for k in self.flushed..integeriser.size() {
+            let a = integeriser.find_value(k).ok_or(JournalError::MissingId(k))?;
+            serde_json::to_writer(&mut writer, a)?;
+            writer.write_all(b"\n")?;
+        }
+        let crc = writer.crc.finish();
+        writeln!(writer.inner, "#crc32 {:08x} {}", crc, portable::widen(fresh))?;
+        writeln!(writer.inner, "#end {}", portable::widen(integeriser.size()))?;
+        writer.inner.flush()?;
+
+        self.flushed = integeriser.size();
+        Ok(fresh)
+    }
+}
+
+/// Reads a journal into a fresh integeriser and returns it together with a
+/// tracker for appending to the same journal.
+/// Fails if the checksum of a batch does not match, or the journal does not
+/// end with an end record.
+pub fn replay<I, R>(reader: R) -> Result<(I, Journal), JournalError>
+where
+    I: Integeriser + Default,
+    I::Item: for<'de> Deserialize<'de>,
+    R: BufRead,
+{
+    trace_span!("replay");
+    let integeriser: I = read_checked(reader)?;
+    let journal = Journal::resume(integeriser.size());
+    Ok((integeriser, journal))
+}
+
+/// Compression of the files written by `save_with_codec`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// No compression.
+    Plain,
+    /// gzip, usually with the extension `.gz`.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard, usually with the extension `.zst`.
+    #[cfg(feature = "zstd-compression")]
+    Zstd,
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd-compression")))]
+fn unsupported(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("compressed journals require the feature `{}`", feature),
+    )
+}
+
+impl Codec {
+    /// Chooses the codec by the extension of `path`, i.e. `Gzip` for `.gz`,
+    /// `Zstd` for `.zst`, and `Plain` otherwise.
+    /// Fails if the feature of the chosen codec is not enabled.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Codec> {
+        match path.as_ref().extension().and_then(OsStr::to_str) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Ok(Codec::Gzip),
+            #[cfg(not(feature = "gzip"))]
+            Some("gz") => Err(unsupported("gzip")),
+            #[cfg(feature = "zstd-compression")]
+            Some("zst") => Ok(Codec::Zstd),
+            #[cfg(not(feature = "zstd-compression"))]
+            Some("zst") => Err(unsupported("zstd-compression")),
+            _ => Ok(Codec::Plain),
+        }
+    }
+}
+
+/// Writes all values of `integeriser` as a journal to the file at `path`,
+/// compressed according to the extension of `path`, see `Codec::from_path`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::persist::{load_from_path, save_to_path, Journal, JournalError};
+///
+/// let mut integeriser = HashIntegeriser::new();
+/// integeriser.integerise("a".to_string());
+/// integeriser.integerise("b".to_string());
+///
+/// let path = std::env::temp_dir().join("integeriser-save-to-path.jsonl");
+/// save_to_path(&integeriser, &path).unwrap();
+/// let restored: HashIntegeriser<String> = load_from_path(&path).unwrap();
+/// assert_eq!(restored, integeriser);
+///
+/// integeriser.integerise("c".to_string());
+/// let file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+/// Journal::resume(2).flush_new(&integeriser, file).unwrap();
+/// let restored: HashIntegeriser<String> = load_from_path(&path).unwrap();
+/// assert_eq!(restored, integeriser);
+///
+/// let contents = std::fs::read(&path).unwrap();
+/// std::fs::write(&path, &contents[..4]).unwrap();
+/// match load_from_path::<HashIntegeriser<String>, _>(&path) {
+///     Err(JournalError::CorruptFile(_)) => (),
+///     _ => panic!("a truncated file must be rejected"),
+/// }
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn save_to_path<I, P>(integeriser: &I, path: P) -> Result<(), JournalError>
+where
+    I: Integeriser,
+    I::Item: Serialize,
+    P: AsRef<Path>,
+{
+    let codec = Codec::from_path(&path)?;
+    save_with_codec(integeriser, path, codec)
+}
+
+/// Writes all values of `integeriser` as a journal to the file at `path`,
+/// compressed with `codec`.
+/// If writing fails, any previous file at `path` is left unchanged.
+pub fn save_with_codec<I, P>(integeriser: &I, path: P, codec: Codec) -> Result<(), JournalError>
+where
+    I: Integeriser,
+    I::Item: Serialize,
+    P: AsRef<Path>,
+{
+    trace_span!("save", path = %path.as_ref().display(), values = integeriser.size(), codec = ?codec);
+    write_atomically(path, codec, |writer| {
+        Journal::new().flush_new(integeriser, writer)?;
+        Ok(())
+    })
+}
+
+/// Writes the file at `path`, compressed with `codec`, via a temporary file
+/// next to it that is renamed to `path` once it is complete.
+pub(crate) fn write_atomically<P, F>(path: P, codec: Codec, write: F) -> Result<(), JournalError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut dyn Write) -> Result<(), JournalError>,
+{
+    let path = path.as_ref();
+    let mut name = match path.file_name() {
+        Some(name) => name.to_os_string(),
+        None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name").into()),
+    };
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+
+    let result = write_encoded(&tmp, codec, write).and_then(|()| Ok(fs::rename(&tmp, path)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+fn write_encoded<F>(path: &Path, codec: Codec, write: F) -> Result<(), JournalError>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), JournalError>,
+{
+    let mut file = BufWriter::new(File::create(path)?);
+    match codec {
+        Codec::Plain => write(&mut file)?,
+        #[cfg(feature = "gzip")]
+        Codec::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(&mut file, flate2::Compression::default());
+            write(&mut encoder)?;
+            encoder.finish()?;
+        }
+        #[cfg(feature = "zstd-compression")]
+        Codec::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(&mut file, 0)?;
+            write(&mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+    Ok(())
+}
+
+/// Parses a number of values, written as a `u64` followed by a line break.
+pub(crate) fn parse_count(field: &[u8]) -> Result<usize, JournalError> {
+    let digits = field.strip_suffix(b"\n").unwrap_or(field);
+    let count = std::str::from_utf8(digits)
+        .ok()
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|digits| digits.parse::<u64>().ok())
+        .ok_or(JournalError::CorruptFile("malformed number of values"))?;
+    Ok(portable::narrow(count)?)
+}
+
+/// Reads a journal of checked batches into a fresh integeriser.
+fn read_checked<I, R>(mut reader: R) -> Result<I, JournalError>
+where
+    I: Integeriser + Default,
+    I::Item: for<'de> Deserialize<'de>,
+    R: BufRead,
+{
+    read_section(&mut reader, &mut Vec::new(), None)
+}
+
+/// Reads a journal of checked batches into a fresh integeriser, up to the
+/// end of `reader` or, if `stop` is given, up to the first line that starts
+/// with `stop`.  That line is left in `line`, which is empty at the end of
+/// `reader`.
+pub(crate) fn read_section<I, R>(reader: &mut R, line: &mut Vec<u8>, stop: Option<&[u8]>) -> Result<I, JournalError>
+where
+    I: Integeriser + Default,
+    I::Item: for<'de> Deserialize<'de>,
+    R: BufRead + ?Sized,
+{
+    let mut integeriser = I::default();
+    let mut crc = Crc32::new();
+    let mut values = 0;
+    let mut batch = 0;
+    let mut ended = false;
+    // errors in the contents are only reported if the checksum matches,
+    // since a damaged journal is reported as such
+    let mut error = None;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', line)? == 0 {
+            break;
+        }
+        if !line.ends_with(b"\n") {
+            return Err(JournalError::CorruptFile("the journal ends within a line"));
+        }
+        if stop.is_some_and(|stop| line.starts_with(stop)) {
+            break;
+        }
+        if let Some(total) = line.strip_prefix(b"#end ") {
+            if batch > 0 || parse_count(total)? != values {
+                return Err(JournalError::CorruptFile("the end record does not match the journal"));
+            }
+            ended = true;
+            continue;
+        }
+        ended = false;
+        if line.starts_with(b"#") {
+            let expected = format!("#crc32 {:08x} ", crc.finish());
+            let count = line.strip_prefix(expected.as_bytes()).ok_or(JournalError::CorruptFile("the checksum does not match the batch"))?;
+            if batch == 0 || parse_count(count)? != batch {
+                return Err(JournalError::CorruptFile("the checksum does not match the batch"));
+            }
+            if let Some(e) = error {
+                return Err(e);
+            }
+            crc = Crc32::new();
+            batch = 0;
+            continue;
+        }
+        crc.update(line);
+        if error.is_none() {
+            match serde_json::from_slice(line) {
+                Ok(a) => {
+                    if integeriser.integerise(a) != values {
+                        error = Some(JournalError::Duplicate(values));
+                    }
+                }
+                Err(e) => error = Some(JournalError::Json(e)),
+            }
+        }
+        values += 1;
+        batch += 1;
+    }
+
+    if batch > 0 {
+        return Err(JournalError::CorruptFile("the checksum line of the last batch is missing"));
+    }
+    if !ended {
+        return Err(JournalError::CorruptFile("the end record is missing"));
+    }
+    trace_event!(values = values, "loaded journal");
+    Ok(integeriser)
+}
+
+/// CRC-32 (as used by gzip and PNG).
+struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        Crc32 { state: !0 }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.state ^= u32::from(b);
+            for _ in 0..8 {
+                self.state = (self.state >> 1) ^ (0xedb8_8320 & (self.state & 1).wrapping_neg());
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+struct Crc32Writer<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads a journal from the file at `path` into a fresh integeriser,
+/// decompressing it according to the extension of `path`, see `Codec::from_path`.
+pub fn load_from_path<I, P>(path: P) -> Result<I, JournalError>
+where
+    I: Integeriser + Default,
+    I::Item: for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+{
+    let codec = Codec::from_path(&path)?;
+    load_with_codec(path, codec)
+}
+
+/// Reads a journal from the file at `path`, compressed with `codec`, into a
+/// fresh integeriser.
+pub fn load_with_codec<I, P>(path: P, codec: Codec) -> Result<I, JournalError>
+where
+    I: Integeriser + Default,
+    I::Item: for<'de> Deserialize<'de>,
+    P: AsRef<Path>,
+{
+    trace_span!("load", path = %path.as_ref().display(), codec = ?codec);
+    read_encoded(path, codec, |reader| read_checked(reader))
+}
+
+/// Reads the file at `path`, compressed with `codec`, with `read`.
+pub(crate) fn read_encoded<T, P, F>(path: P, codec: Codec, read: F) -> Result<T, JournalError>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut dyn BufRead) -> Result<T, JournalError>,
+{
+    let file = File::open(path)?;
+    match codec {
+        Codec::Plain => read(&mut BufReader::new(file)),
+        #[cfg(feature = "gzip")]
+        Codec::Gzip => read(&mut BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        #[cfg(feature = "zstd-compression")]
+        Codec::Zstd => read(&mut BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{load_from_path, replay, save_to_path, Crc32, Journal, JournalError};
+    use {HashIntegeriser, Integeriser};
+
+    type Strings = HashIntegeriser<String>;
+
+    fn integeriser(values: &[&str]) -> Strings {
+        let mut integeriser = HashIntegeriser::new();
+        for a in values {
+            integeriser.integerise(a.to_string());
+        }
+        integeriser
+    }
+
+    fn two_batches() -> Vec<u8> {
+        let mut log = Vec::new();
+        let mut journal = Journal::new();
+        journal.flush_new(&integeriser(&["a", "b"]), &mut log).unwrap();
+        journal.flush_new(&integeriser(&["a", "b", "c"]), &mut log).unwrap();
+        log
+    }
+
+    fn is_corrupt(result: Result<(Strings, Journal), JournalError>) -> bool {
+        matches!(result, Err(JournalError::CorruptFile(_)))
+    }
+
+    #[test]
+    fn empty_input_is_rejected() {
+        assert!(is_corrupt(replay(&b""[..])));
+    }
+
+    #[test]
+    fn empty_integerisers_are_restored() {
+        let mut log = Vec::new();
+        assert_eq!(Journal::new().flush_new(&Strings::new(), &mut log).unwrap(), 0);
+        assert_eq!(log, b"#end 0\n");
+        let (restored, journal) = replay::<Strings, _>(&log[..]).unwrap();
+        assert!(restored.is_empty());
+        assert_eq!(journal.flushed(), 0);
+    }
+
+    #[test]
+    fn batches_are_appended() {
+        let log = two_batches();
+        let (restored, journal) = replay::<Strings, _>(&log[..]).unwrap();
+        assert_eq!(restored, integeriser(&["a", "b", "c"]));
+        assert_eq!(journal.flushed(), 3);
+    }
+
+    #[test]
+    fn cuts_at_a_batch_boundary_are_rejected() {
+        let log = String::from_utf8(two_batches()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        for &end in &[lines.len() - 1, 3] {
+            assert!(lines[end - 1].starts_with("#crc32"));
+            let cut = lines[..end].join("\n") + "\n";
+            assert!(is_corrupt(replay(cut.as_bytes())), "cut after {} lines", end);
+        }
+    }
+
+    #[test]
+    fn a_cut_after_an_end_record_restores_the_earlier_journal() {
+        let log = String::from_utf8(two_batches()).unwrap();
+        let cut = log.lines().take(4).collect::<Vec<_>>().join("\n") + "\n";
+        let (restored, _) = replay::<Strings, _>(cut.as_bytes()).unwrap();
+        assert_eq!(restored, integeriser(&["a", "b"]));
+    }
+
+    #[test]
+    fn damaged_values_are_rejected() {
+        let log = String::from_utf8(two_batches()).unwrap().replacen("\"b\"", "\"x\"", 1);
+        assert!(is_corrupt(replay(log.as_bytes())));
+    }
+
+    #[test]
+    fn wrong_end_records_are_rejected() {
+        let log = String::from_utf8(two_batches()).unwrap().replacen("#end 2", "#end 1", 1);
+        assert!(is_corrupt(replay(log.as_bytes())));
+    }
+
+    #[test]
+    fn malformed_counts_are_rejected() {
+        for end in &["#end +2", "#end 2 ", "#end 18446744073709551616"] {
+            let log = String::from_utf8(two_batches()).unwrap().replacen("#end 2", end, 1);
+            assert!(is_corrupt(replay(log.as_bytes())));
+        }
+        if cfg!(target_pointer_width = "32") {
+            match replay::<Strings, _>(&b"#end 4294967296\n"[..]) {
+                Err(JournalError::Overflow(_)) => (),
+                other => panic!("unexpected {:?}", other.map(|p| p.0)),
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_with_a_valid_checksum_are_reported() {
+        let batch = "\"a\"\n\"a\"\n";
+        let mut crc = Crc32::new();
+        crc.update(batch.as_bytes());
+        let log = format!("{}#crc32 {:08x} 2\n#end 2\n", batch, crc.finish());
+        match replay::<Strings, _>(log.as_bytes()) {
+            Err(JournalError::Duplicate(1)) => (),
+            other => panic!("unexpected {:?}", other.map(|p| p.0)),
+        }
+    }
+
+    #[test]
+    fn shrunk_integerisers_are_rejected() {
+        let mut journal = Journal::resume(3);
+        match journal.flush_new(&integeriser(&["a"]), Vec::new()) {
+            Err(JournalError::Truncated) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn failed_saves_leave_the_target_alone() {
+        let dir = std::env::temp_dir().join("integeriser-persist-failed-save");
+        fs::create_dir_all(dir.join("occupied")).unwrap();
+        assert!(save_to_path(&integeriser(&["a"]), &dir).is_err());
+        assert!(dir.join("occupied").is_dir());
+        assert!(!dir.with_file_name("integeriser-persist-failed-save.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_files_are_complete() {
+        let path = std::env::temp_dir().join("integeriser-persist-complete.jsonl");
+        save_to_path(&integeriser(&["a", "b"]), &path).unwrap();
+        assert_eq!(load_from_path::<Strings, _>(&path).unwrap(), integeriser(&["a", "b"]));
+
+        fs::write(&path, b"").unwrap();
+        match load_from_path::<Strings, _>(&path) {
+            Err(JournalError::CorruptFile(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! (De)serialising integers with a fixed width of 64 bits, independently of
+//! the width of `usize` on the serialising and the deserialising machine.
+//!
+//! The `serde` implementations of the integerisers only store their values,
+//! whose integers are implicit in their order.  Integers stored elsewhere,
+//! e.g. in fields of type `usize`, can be made portable with
+//! `#[serde(with = "integeriser::portable")]` or, for `Vec<usize>`,
+//! `#[serde(with = "integeriser::portable::seq")]`.
+//! Deserialising an integer that does not fit into `usize` on the current
+//! machine fails with an error.
+//!
+//! The formats of this crate (the journals of `persist`, the files of
+//! `registry` and the vocabularies of `huggingface`) store their integers as
+//! `u64` with `widen` and read them with `narrow`, and fail with `Overflow`
+//! if an integer does not fit into `usize`.
+//!
+//! # Example
+//!
+//! ```
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate serde_json;
+//! extern crate integeriser;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Rule {
+//!     #[serde(with = "integeriser::portable")]
+//!     lhs: usize,
+//!     #[serde(with = "integeriser::portable::seq")]
+//!     rhs: Vec<usize>,
+//! }
+//!
+//! # fn main() {
+//! let rule = Rule { lhs: 0, rhs: vec![1, 2] };
+//! let json = serde_json::to_string(&rule).unwrap();
+//! assert_eq!(serde_json::from_str::<Rule>(&json).unwrap(), rule);
+//!
+//! # if cfg!(target_pointer_width = "32") {
+//! assert!(serde_json::from_str::<Rule>(r#"{"lhs":4294967296,"rhs":[]}"#).is_err());
+//! # }
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Error for an integer that does not fit into `usize` on this machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Overflow(pub u64);
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the integer {} does not fit into usize on this machine", self.0)
+    }
+}
+
+impl error::Error for Overflow {}
+
+/// The integer `k` with a fixed width of 64 bits.
+pub fn widen(k: usize) -> u64 {
+    k as u64
+}
+
+/// The integer `k` as a `usize`, if it fits.
+pub fn narrow(k: u64) -> Result<usize, Overflow> {
+    usize::try_from(k).map_err(|_| Overflow(k))
+}
+
+/// Serialises `k` as a `u64`.
+pub fn serialize<S: Serializer>(k: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(widen(*k))
+}
+
+/// Deserialises a `u64` as a `usize`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    narrow(u64::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+/// (De)serialising sequences of integers with a fixed width of 64 bits.
+pub mod seq {
+    use std::fmt;
+
+    use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+    use serde::Serializer;
+
+    /// Serialises `ks` as a sequence of `u64`.
+    pub fn serialize<S: Serializer>(ks: &[usize], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(ks.iter().map(|&k| super::widen(k)))
+    }
+
+    /// Deserialises a sequence of `u64` as a `Vec<usize>`.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<usize>, D::Error> {
+        struct SeqVisitor;
+
+        impl<'de> Visitor<'de> for SeqVisitor {
+            type Value = Vec<usize>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence of integers")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<usize>, A::Error> {
+                let mut ks = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(k) = seq.next_element::<u64>()? {
+                    ks.push(super::narrow(k).map_err(A::Error::custom)?);
+                }
+                Ok(ks)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor)
+    }
+}
@@ -0,0 +1,411 @@
+//! Several named integerisers that belong together, e.g. the vocabularies
+//! of the fields of a data set.
+
+use std::ops::{Index, IndexMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "persistence")]
+use std::io::BufRead;
+#[cfg(feature = "persistence")]
+use std::path::Path;
+
+#[cfg(feature = "serialisation")]
+use std::fmt;
+#[cfg(feature = "serialisation")]
+use std::marker::PhantomData;
+
+#[cfg(feature = "serialisation")]
+use serde::de::{MapAccess, Visitor};
+#[cfg(feature = "serialisation")]
+use serde::ser::SerializeMap;
+#[cfg(feature = "serialisation")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use budget::{footprint, MemSize};
+#[cfg(feature = "persistence")]
+use persist::{self, Codec, Journal, JournalError};
+#[cfg(feature = "persistence")]
+use portable;
+use Integeriser;
+
+/// Number of the next registry, unique within the process.
+static NEXT_REGISTRY: AtomicUsize = AtomicUsize::new(0);
+
+/// Handle of an integeriser in an `IntegeriserRegistry`, see
+/// `IntegeriserRegistry::register`.
+/// It belongs to the registry that returned it and to the clones of that
+/// registry; indexing any other registry with it panics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Handle {
+    registry: usize,
+    index: usize,
+}
+
+/// Size of an integeriser and estimated footprint of its values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Stats {
+    /// Number of values.
+    pub size: usize,
+    /// Estimated number of bytes occupied by the values, see `budget::Budgeted`.
+    pub bytes: usize,
+}
+
+/// Integerisers of type `I` with distinct names, in the order of their
+/// registration.  They are accessed by name or, without looking up the
+/// name, by the `Handle` returned by `register`.
+/// Indexing with a handle of a different registry panics.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::registry::IntegeriserRegistry;
+///
+/// let mut registry: IntegeriserRegistry<HashIntegeriser<String>> = IntegeriserRegistry::new();
+/// let tokens = registry.register("tokens");
+/// let pos = registry.register("pos");
+///
+/// for (token, tag) in &[("the", "DET"), ("cat", "NOUN"), ("the", "DET")] {
+///     registry[tokens].integerise(token.to_string());
+///     registry[pos].integerise(tag.to_string());
+/// }
+///
+/// assert_eq!(registry.register("pos"), pos);
+/// assert_eq!(registry.get("tokens").map(Integeriser::size), Some(2));
+/// assert_eq!(registry.stats(pos).size, 2);
+/// assert_eq!(registry.names().collect::<Vec<_>>(), vec!["tokens", "pos"]);
+/// ```
+///
+/// ```should_panic
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::registry::IntegeriserRegistry;
+///
+/// let mut registry: IntegeriserRegistry<HashIntegeriser<String>> = IntegeriserRegistry::new();
+/// let mut other: IntegeriserRegistry<HashIntegeriser<String>> = IntegeriserRegistry::new();
+/// other.register("tokens");
+/// let tokens = registry.register("tokens");
+/// other[tokens].size(); // panics: the handle belongs to `registry`
+/// ```
+#[derive(Clone, Debug)]
+pub struct IntegeriserRegistry<I> {
+    id: usize,
+    names: Vec<String>,
+    integerisers: Vec<I>,
+}
+
+impl<I> IntegeriserRegistry<I> {
+    /// Constructs a new, empty registry.
+    pub fn new() -> Self {
+        IntegeriserRegistry {
+            id: NEXT_REGISTRY.fetch_add(1, Ordering::Relaxed),
+            names: Vec::new(),
+            integerisers: Vec::new(),
+        }
+    }
+
+    /// Returns the handle of the integeriser called `name`, registering a
+    /// new, empty integeriser under that name if there is none.
+    pub fn register<N: AsRef<str> + Into<String>>(&mut self, name: N) -> Handle
+    where
+        I: Default,
+    {
+        match self.find(name.as_ref()) {
+            Some(h) => h,
+            None => self.insert(name.into(), I::default()),
+        }
+    }
+
+    fn insert(&mut self, name: String, integeriser: I) -> Handle {
+        self.names.push(name);
+        self.integerisers.push(integeriser);
+        Handle { registry: self.id, index: self.names.len() - 1 }
+    }
+
+    /// The handle of the integeriser called `name`.
+    pub fn find(&self, name: &str) -> Option<Handle> {
+        self.names.iter().position(|n| n == name).map(|index| Handle { registry: self.id, index })
+    }
+
+    /// The integeriser called `name`.
+    pub fn get(&self, name: &str) -> Option<&I> {
+        self.find(name).map(|h| &self[h])
+    }
+
+    /// The mutable integeriser called `name`.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut I> {
+        self.find(name).map(move |h| &mut self[h])
+    }
+
+    /// The name of the integeriser with the handle `h`.
+    pub fn name(&self, h: Handle) -> &str {
+        &self.names[self.index_of(h)]
+    }
+
+    fn index_of(&self, h: Handle) -> usize {
+        assert_eq!(h.registry, self.id, "the handle belongs to a different registry");
+        h.index
+    }
+
+    /// Names of all integerisers in the order of their registration.
+    pub fn names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// All integerisers and their names in the order of their registration.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a I)> + 'a {
+        self.names().zip(&self.integerisers)
+    }
+
+    /// Number of registered integerisers.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether no integeriser is registered.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Size and footprint of the integeriser with the handle `h`.
+    pub fn stats(&self, h: Handle) -> Stats
+    where
+        I: Integeriser,
+        I::Item: MemSize,
+    {
+        let integeriser = &self[h];
+        let bytes = (0..integeriser.size())
+            .filter_map(|k| integeriser.find_value(k))
+            .map(footprint)
+            .sum();
+        Stats { size: integeriser.size(), bytes }
+    }
+}
+
+impl<I: PartialEq> PartialEq for IntegeriserRegistry<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.names == other.names && self.integerisers == other.integerisers
+    }
+}
+
+impl<I: Eq> Eq for IntegeriserRegistry<I> {}
+
+impl<I> Default for IntegeriserRegistry<I> {
+    fn default() -> Self {
+        IntegeriserRegistry::new()
+    }
+}
+
+impl<I> Index<Handle> for IntegeriserRegistry<I> {
+    type Output = I;
+
+    fn index(&self, h: Handle) -> &I {
+        &self.integerisers[self.index_of(h)]
+    }
+}
+
+impl<I> IndexMut<Handle> for IntegeriserRegistry<I> {
+    fn index_mut(&mut self, h: Handle) -> &mut I {
+        let index = self.index_of(h);
+        &mut self.integerisers[index]
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl<I: Integeriser> IntegeriserRegistry<I> {
+    /// Writes all integerisers to the file at `path`, compressed according
+    /// to the extension of `path` (see `persist::Codec::from_path`), as a
+    /// line `#registry <number of integerisers>` followed by one section
+    /// per integeriser: a line `#vocabulary <name as JSON>` and the journal
+    /// of its values (see `persist`).
+    /// If writing fails, any previous file at `path` is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    /// use integeriser::registry::IntegeriserRegistry;
+    ///
+    /// let mut registry: IntegeriserRegistry<HashIntegeriser<String>> = IntegeriserRegistry::new();
+    /// let labels = registry.register("labels");
+    /// registry[labels].integerise("positive".to_string());
+    /// registry.register("tokens");
+    ///
+    /// let path = std::env::temp_dir().join("integeriser-registry.jsonl");
+    /// registry.save_to_path(&path).unwrap();
+    /// assert_eq!(IntegeriserRegistry::load_from_path(&path).unwrap(), registry);
+    ///
+    /// let contents = std::fs::read_to_string(&path).unwrap();
+    /// let cut = &contents[..contents.find("#vocabulary \"tokens\"").unwrap()];
+    /// std::fs::write(&path, cut).unwrap();
+    /// assert!(IntegeriserRegistry::<HashIntegeriser<String>>::load_from_path(&path).is_err());
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    pub fn save_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), JournalError>
+    where
+        I::Item: Serialize,
+    {
+        trace_span!("save_registry", path = %path.as_ref().display(), integerisers = self.len());
+        let codec = Codec::from_path(&path)?;
+        persist::write_atomically(path, codec, |writer| {
+            writeln!(writer, "#registry {}", portable::widen(self.len()))?;
+            for (name, integeriser) in self.iter() {
+                writeln!(writer, "#vocabulary {}", serde_json::to_string(name)?)?;
+                Journal::new().flush_new(integeriser, &mut *writer)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Reads all integerisers from a file written by `save_to_path`.
+    /// Fails if a section is missing or damaged.
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, JournalError>
+    where
+        I: Default,
+        I::Item: for<'de> Deserialize<'de>,
+    {
+        trace_span!("load_registry", path = %path.as_ref().display());
+        let codec = Codec::from_path(&path)?;
+        persist::read_encoded(path, codec, |reader| IntegeriserRegistry::read_sections(reader))
+    }
+
+    fn read_sections(reader: &mut dyn BufRead) -> Result<Self, JournalError>
+    where
+        I: Default,
+        I::Item: for<'de> Deserialize<'de>,
+    {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        let count = match header.strip_prefix("#registry ") {
+            Some(n) => persist::parse_count(n.as_bytes())?,
+            None => return Err(JournalError::CorruptFile("the registry header is missing")),
+        };
+
+        let mut registry = IntegeriserRegistry::new();
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line)?;
+        while !line.is_empty() {
+            let name: String = match line.strip_prefix(b"#vocabulary ") {
+                Some(name) => serde_json::from_slice(name)?,
+                None => return Err(JournalError::CorruptFile("a section does not start with its name")),
+            };
+            if registry.find(&name).is_some() {
+                return Err(JournalError::CorruptFile("a name occurs in more than one section"));
+            }
+            let integeriser = persist::read_section(reader, &mut line, Some(b"#vocabulary "))?;
+            registry.insert(name, integeriser);
+        }
+        if registry.len() != count {
+            return Err(JournalError::CorruptFile("sections of the registry are missing"));
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<I: Serialize> Serialize for IntegeriserRegistry<I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (name, integeriser) in self.iter() {
+            map.serialize_entry(name, integeriser)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serialisation")]
+struct RegistryVisitor<I>(PhantomData<fn() -> I>);
+
+#[cfg(feature = "serialisation")]
+impl<'de, I: Deserialize<'de>> Visitor<'de> for RegistryVisitor<I> {
+    type Value = IntegeriserRegistry<I>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map from distinct names to integerisers")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(self, mut access: M) -> Result<Self::Value, M::Error> {
+        let mut registry = IntegeriserRegistry::new();
+        while let Some((name, integeriser)) = access.next_entry::<String, I>()? {
+            if registry.find(&name).is_some() {
+                return Err(serde::de::Error::custom(format!("duplicate name {:?}", name)));
+            }
+            registry.insert(name, integeriser);
+        }
+        Ok(registry)
+    }
+}
+
+#[cfg(feature = "serialisation")]
+impl<'de, I: Deserialize<'de>> Deserialize<'de> for IntegeriserRegistry<I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(RegistryVisitor(PhantomData))
+    }
+}
+
+#[cfg(all(test, feature = "persistence"))]
+mod tests {
+    use std::fs;
+
+    use super::IntegeriserRegistry;
+    use persist::JournalError;
+    use {HashIntegeriser, Integeriser};
+
+    type Registry = IntegeriserRegistry<HashIntegeriser<String>>;
+
+    fn registry() -> Registry {
+        let mut registry = Registry::new();
+        let tokens = registry.register("tokens");
+        registry[tokens].integerise("a".to_string());
+        registry[tokens].integerise("b".to_string());
+        registry.register("empty");
+        let labels = registry.register("labels");
+        registry[labels].integerise("#vocabulary".to_string());
+        registry
+    }
+
+    fn assert_corrupt(contents: &[u8], name: &str) {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, contents).unwrap();
+        match Registry::load_from_path(&path) {
+            Err(JournalError::CorruptFile(_)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn registries_round_trip_compressed() {
+        let path = std::env::temp_dir().join("integeriser-registry-round-trip.jsonl.gz");
+        registry().save_to_path(&path).unwrap();
+        assert_ne!(&fs::read(&path).unwrap()[..1], b"#");
+        let loaded = Registry::load_from_path(&path).unwrap();
+        assert_eq!(loaded, registry());
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["tokens", "empty", "labels"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn damaged_registries_are_rejected() {
+        let path = std::env::temp_dir().join("integeriser-registry-damaged.jsonl");
+        registry().save_to_path(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_corrupt(b"", "integeriser-registry-empty.jsonl");
+        let last = contents.find("#vocabulary \"labels\"").unwrap();
+        assert_corrupt(&contents.as_bytes()[..last], "integeriser-registry-cut.jsonl");
+        let doubled = contents.replacen("\"empty\"", "\"tokens\"", 1);
+        assert_corrupt(doubled.as_bytes(), "integeriser-registry-doubled.jsonl");
+        let damaged = contents.replacen("\"b\"", "\"c\"", 1);
+        assert_corrupt(damaged.as_bytes(), "integeriser-registry-damaged.jsonl");
+    }
+
+    #[test]
+    #[should_panic(expected = "different registry")]
+    fn handles_of_clones_are_told_apart_from_others() {
+        let registry = registry();
+        let clone = registry.clone();
+        let tokens = clone.find("tokens").unwrap();
+        assert_eq!(registry[tokens].size(), 2);
+        Registry::new().name(tokens);
+    }
+}
@@ -0,0 +1,66 @@
+//! Scanning text for occurrences of the strings stored in an integeriser.
+
+use std::ops::Range;
+
+use aho_corasick::{AhoCorasick, BuildError, MatchKind};
+
+use Integeriser;
+
+/// Aho–Corasick automaton over the values of a string integeriser.
+/// The automaton is a snapshot: values that are integerised after its
+/// construction are not found.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::scanner::Scanner;
+///
+/// let mut gazetteer = HashIntegeriser::new();
+/// let new_york = gazetteer.integerise("New York");
+/// let york = gazetteer.integerise("York");
+///
+/// let scanner = Scanner::new(&gazetteer).unwrap();
+/// let matches: Vec<_> = scanner.find_iter("From York to New York.").collect();
+///
+/// assert_eq!(matches, vec![(5..9, york), (13..21, new_york)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scanner {
+    automaton: AhoCorasick,
+    ids: Vec<usize>,
+}
+
+impl Scanner {
+    /// Compiles the automaton from all non-empty values currently stored in `integeriser`.
+    pub fn new<I, S>(integeriser: &I) -> Result<Self, BuildError>
+    where
+        I: Integeriser<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut patterns = Vec::new();
+        let mut ids = Vec::new();
+        for k in 0..integeriser.size() {
+            if let Some(value) = integeriser.find_value(k) {
+                if !value.as_ref().is_empty() {
+                    patterns.push(value.as_ref());
+                    ids.push(k);
+                }
+            }
+        }
+
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(patterns)?;
+        Ok(Scanner { automaton, ids })
+    }
+
+    /// Iterates over the non-overlapping occurrences of values in `text`,
+    /// from left to right, preferring the longest value at every position.
+    /// Every occurrence is given by its byte range in `text` and the integer of the value.
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> impl Iterator<Item = (Range<usize>, usize)> + 'a {
+        self.automaton
+            .find_iter(text)
+            .map(move |m| (m.range(), self.ids[m.pattern().as_usize()]))
+    }
+}
@@ -0,0 +1,228 @@
+//! Segmentation of text into the strings stored in an integeriser.
+
+use std::collections::BTreeMap;
+
+use Integeriser;
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    children: BTreeMap<u8, usize>,
+    id: Option<usize>,
+}
+
+/// Byte-wise prefix tree over the values of a string integeriser.
+/// The tree is a snapshot: values that are integerised after its
+/// construction are not known to it.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::segment::PrefixIndex;
+///
+/// let mut vocab = HashIntegeriser::new();
+/// for piece in &["un", "unbe", "liev", "able", "[UNK]"] {
+///     vocab.integerise(piece.to_string());
+/// }
+///
+/// let index = PrefixIndex::new(&vocab);
+/// let ids = index.encode_greedy("unbelievable!", |_, ids| ids.push(4));
+///
+/// assert_eq!(ids, vec![1, 2, 3, 4]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrefixIndex {
+    nodes: Vec<Node>,
+}
+
+impl PrefixIndex {
+    /// Builds the prefix tree over all values currently stored in `integeriser`.
+    pub fn new<I, S>(integeriser: &I) -> Self
+    where
+        I: Integeriser<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut index = PrefixIndex { nodes: vec![Node::default()] };
+        for k in 0..integeriser.size() {
+            if let Some(value) = integeriser.find_value(k) {
+                index.insert(value.as_ref(), k);
+            }
+        }
+        index
+    }
+
+    fn insert(&mut self, s: &str, k: usize) {
+        let mut node = 0;
+        for &b in s.as_bytes() {
+            let next = self.nodes.len();
+            node = *self.nodes[node].children.entry(b).or_insert(next);
+            if node == next {
+                self.nodes.push(Node::default());
+            }
+        }
+        self.nodes[node].id = Some(k);
+    }
+
+    /// Returns the id and the length (in bytes) of the longest value that is
+    /// a non-empty prefix of `s`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::{Integeriser, HashIntegeriser};
+    /// use integeriser::segment::PrefixIndex;
+    ///
+    /// let mut lexicon = HashIntegeriser::new();
+    /// lexicon.integerise("New");
+    /// let new_york = lexicon.integerise("New York");
+    ///
+    /// let index = PrefixIndex::new(&lexicon);
+    ///
+    /// assert_eq!(index.longest_prefix_match("New York City"), Some((new_york, 8)));
+    /// assert_eq!(index.longest_prefix_match("Newark"), Some((0, 3)));
+    /// assert_eq!(index.longest_prefix_match("York"), None);
+    /// ```
+    pub fn longest_prefix_match(&self, s: &str) -> Option<(usize, usize)> {
+        let mut node = 0;
+        let mut best = None;
+        for (i, b) in s.bytes().enumerate() {
+            match self.nodes[node].children.get(&b) {
+                Some(&next) => node = next,
+                None => break,
+            }
+            if let Some(k) = self.nodes[node].id {
+                best = Some((k, i + 1));
+            }
+        }
+        best
+    }
+
+    /// Splits `text` from left to right into the longest known values
+    /// (maximal munch) and returns their ids.
+    /// Every maximal span of `text` in which no known value starts is passed
+    /// to `fallback`, which may append any number of ids for it.
+    pub fn encode_greedy<F>(&self, text: &str, mut fallback: F) -> Vec<usize>
+    where
+        F: FnMut(&str, &mut Vec<usize>),
+    {
+        let mut ids = Vec::new();
+        let mut uncovered = None;
+        let mut pos = 0;
+
+        while pos < text.len() {
+            match self.longest_prefix_match(&text[pos..]) {
+                Some((k, len)) => {
+                    if let Some(start) = uncovered.take() {
+                        fallback(&text[start..pos], &mut ids);
+                    }
+                    ids.push(k);
+                    pos += len;
+                }
+                None => {
+                    uncovered.get_or_insert(pos);
+                    pos += text[pos..].chars().next().map_or(1, char::len_utf8);
+                }
+            }
+        }
+        if let Some(start) = uncovered {
+            fallback(&text[start..], &mut ids);
+        }
+
+        ids
+    }
+}
+
+/// Number of ids that are reserved for byte pieces, see `reserve_byte_pieces`.
+pub const BYTE_PIECES: usize = 256;
+
+/// The value that stands for the byte `b`, e.g. `<0x41>` for `b'A'`.
+pub fn byte_piece(b: u8) -> String {
+    format!("<0x{:02X}>", b)
+}
+
+/// Integerises the byte pieces of all 256 byte values, so that the piece of
+/// byte `b` gets the id `b`.
+///
+/// # Panics
+///
+/// Panics if `integeriser` is not empty.
+pub fn reserve_byte_pieces<I: Integeriser<Item = String>>(integeriser: &mut I) {
+    assert_eq!(integeriser.size(), 0, "byte pieces must be reserved in an empty integeriser");
+    for b in 0..BYTE_PIECES {
+        integeriser.integerise(byte_piece(b as u8));
+    }
+}
+
+/// Greedy encoder that falls back to the ids of the individual bytes for
+/// every span of the text that is not covered by a known value.
+/// Encoding is therefore total, and `decode` recovers the original text.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::segment::{reserve_byte_pieces, ByteFallbackEncoder};
+///
+/// let mut vocab = HashIntegeriser::new();
+/// reserve_byte_pieces(&mut vocab);
+/// let hello = vocab.integerise("hello".to_string());
+///
+/// let encoder = ByteFallbackEncoder::new(&vocab);
+/// let ids = encoder.encode("hello, <0x41>");
+///
+/// assert_eq!(ids[0], hello);
+/// assert!(ids[1..].iter().all(|&k| k < 256));
+/// assert_eq!(encoder.decode(&vocab, &ids), Some("hello, <0x41>".to_string()));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ByteFallbackEncoder {
+    index: PrefixIndex,
+}
+
+impl ByteFallbackEncoder {
+    /// Builds the encoder over all values currently stored in `integeriser`.
+    /// The byte pieces themselves are not matched in the text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ids `0` to `255` are not the byte pieces, see `reserve_byte_pieces`.
+    pub fn new<I: Integeriser<Item = String>>(integeriser: &I) -> Self {
+        for b in 0..BYTE_PIECES {
+            assert_eq!(
+                integeriser.find_value(b),
+                Some(&byte_piece(b as u8)),
+                "id {} is not reserved for its byte piece",
+                b
+            );
+        }
+
+        let mut index = PrefixIndex { nodes: vec![Node::default()] };
+        for k in BYTE_PIECES..integeriser.size() {
+            if let Some(value) = integeriser.find_value(k) {
+                index.insert(value, k);
+            }
+        }
+        ByteFallbackEncoder { index }
+    }
+
+    /// Splits `text` greedily into known values and bytes and returns their ids.
+    pub fn encode(&self, text: &str) -> Vec<usize> {
+        self.index
+            .encode_greedy(text, |span, ids| ids.extend(span.bytes().map(usize::from)))
+    }
+
+    /// Concatenates the values and bytes that correspond to `ids`.
+    /// Returns `None` if an id is unknown to `integeriser` or if the bytes
+    /// do not form valid UTF-8.
+    pub fn decode<I: Integeriser<Item = String>>(&self, integeriser: &I, ids: &[usize]) -> Option<String> {
+        let mut bytes = Vec::new();
+        for &k in ids {
+            if k < BYTE_PIECES {
+                bytes.push(k as u8);
+            } else {
+                bytes.extend_from_slice(integeriser.find_value(k)?.as_bytes());
+            }
+        }
+        String::from_utf8(bytes).ok()
+    }
+}
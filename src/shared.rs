@@ -0,0 +1,211 @@
+//! Integerisers whose integers are agreed on by several processes through
+//! a Redis server.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use redis::{ConnectionLike, FromRedisValue, RedisError, RedisResult, Script, ToRedisArgs};
+
+use bloom::TryIntegeriser;
+
+/// Looks up the integer of `ARGV[1]` in the hash `KEYS[1]`, or assigns it
+/// the next value of the counter `KEYS[2]` and records it in both `KEYS[1]`
+/// and the reverse hash `KEYS[3]`.  Runs atomically on the server.
+const INTEGERISE: &str = r"
+local k = redis.call('HGET', KEYS[1], ARGV[1])
+if k then
+    return tonumber(k)
+end
+k = redis.call('INCR', KEYS[2]) - 1
+redis.call('HSETNX', KEYS[1], ARGV[1], k)
+redis.call('HSET', KEYS[3], k, ARGV[1])
+return k
+";
+
+/// Integeriser whose mapping is stored on a Redis server under the keys
+/// `{<namespace>}:ids`, `{<namespace>}:values` and `{<namespace>}:next`,
+/// so that every process that uses the same namespace gets the same
+/// integers for the same values.  New values are integerised by an atomic
+/// script, and all values that were integerised or looked up are cached
+/// locally.  The braces keep all keys of a namespace in the same slot of a
+/// Redis cluster.
+///
+/// Since the local cache only holds some of the values, and every operation
+/// may fail on the server, this structure does not implement `Integeriser`.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate redis;
+/// # extern crate integeriser;
+/// use integeriser::shared::RedisIntegeriser;
+///
+/// # fn main() -> redis::RedisResult<()> {
+/// let connection = redis::Client::open("redis://127.0.0.1/")?.get_connection()?;
+/// let mut integeriser = RedisIntegeriser::new(connection, "vocab");
+///
+/// let k = integeriser.integerise("cat".to_string())?;
+/// assert_eq!(integeriser.find_key(&"cat".to_string())?, Some(k));
+/// assert_eq!(integeriser.find_value(k)?, Some(&"cat".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+pub struct RedisIntegeriser<A: Eq + Hash, C> {
+    connection: C,
+    namespace: String,
+    script: Script,
+    keys: HashMap<A, usize>,
+    values: HashMap<usize, A>,
+}
+
+impl<A: Eq + Hash + fmt::Debug, C> fmt::Debug for RedisIntegeriser<A, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RedisIntegeriser")
+            .field("namespace", &self.namespace)
+            .field("cached", &self.keys)
+            .finish()
+    }
+}
+
+impl<A, C> RedisIntegeriser<A, C>
+where
+    A: Clone + Eq + Hash + ToRedisArgs + FromRedisValue,
+    C: ConnectionLike,
+{
+    /// Constructs an integeriser that stores its mapping under `namespace`
+    /// on the server of `connection`, with an empty local cache.
+    pub fn new<N: Into<String>>(connection: C, namespace: N) -> Self {
+        RedisIntegeriser {
+            connection,
+            namespace: namespace.into(),
+            script: Script::new(INTEGERISE),
+            keys: HashMap::new(),
+            values: HashMap::new(),
+        }
+    }
+
+    fn key(&self, suffix: &str) -> String {
+        format!("{{{}}}:{}", self.namespace, suffix)
+    }
+
+    fn cache(&mut self, a: A, k: usize) {
+        self.values.insert(k, a.clone());
+        self.keys.insert(a, k);
+    }
+
+    /// Returns the integer that the server assigns to the value `a`.
+    /// The integers are assigned consecutively starting from `0` across
+    /// all processes that use the same namespace.
+    pub fn integerise(&mut self, a: A) -> RedisResult<usize> {
+        if let Some(&k) = self.keys.get(&a) {
+            return Ok(k);
+        }
+        let k = self
+            .script
+            .key(self.key("ids"))
+            .key(self.key("next"))
+            .key(self.key("values"))
+            .arg(&a)
+            .invoke(&mut self.connection)?;
+        self.cache(a, k);
+        Ok(k)
+    }
+
+    /// Lookup the value that corresponds to the integer `k: usize`, asking
+    /// the server if it is not cached.
+    pub fn find_value(&mut self, k: usize) -> RedisResult<Option<&A>> {
+        if !self.values.contains_key(&k) {
+            let a: Option<A> = redis::cmd("HGET")
+                .arg(self.key("values"))
+                .arg(k)
+                .query(&mut self.connection)?;
+            match a {
+                Some(a) => self.cache(a, k),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.values.get(&k))
+    }
+
+    /// Lookup the integer that corresponds to the value `a: A`, asking the
+    /// server if it is not cached.
+    pub fn find_key(&mut self, a: &A) -> RedisResult<Option<usize>> {
+        if let Some(&k) = self.keys.get(a) {
+            return Ok(Some(k));
+        }
+        let k: Option<usize> = redis::cmd("HGET")
+            .arg(self.key("ids"))
+            .arg(a)
+            .query(&mut self.connection)?;
+        if let Some(k) = k {
+            self.cache(a.clone(), k);
+        }
+        Ok(k)
+    }
+
+    /// Number of distinct values that are stored on the server.
+    pub fn size(&mut self) -> RedisResult<usize> {
+        redis::cmd("HLEN").arg(self.key("ids")).query(&mut self.connection)
+    }
+
+    /// Number of values that are cached locally.
+    pub fn cached(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Empties the local cache.
+    pub fn clear_cache(&mut self) {
+        self.keys.clear();
+        self.values.clear();
+    }
+
+    /// Unwraps the connection.
+    pub fn into_inner(self) -> C {
+        self.connection
+    }
+}
+
+/// The lookups of unknown values can be answered locally by a
+/// `bloom::TryBloomFiltered`.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate redis;
+/// # extern crate integeriser;
+/// use integeriser::bloom::{TryBloomFiltered, TryIntegeriser};
+/// use integeriser::shared::RedisIntegeriser;
+///
+/// # fn main() -> redis::RedisResult<()> {
+/// let connection = redis::Client::open("redis://127.0.0.1/")?.get_connection()?;
+/// let known = vec!["cat".to_string(), "dog".to_string()];
+///
+/// let mut integeriser = TryBloomFiltered::new(RedisIntegeriser::new(connection, "vocab"), 1_000_000, 0.01);
+/// for a in &known {
+///     integeriser.insert(a);
+/// }
+///
+/// // answered locally unless the filter reports a false positive
+/// assert_eq!(integeriser.try_find_key(&"unicorn".to_string())?, None);
+/// let k = integeriser.try_integerise("fish".to_string())?;
+/// assert_eq!(integeriser.try_find_key(&"fish".to_string())?, Some(k));
+/// # Ok(())
+/// # }
+/// ```
+impl<A, C> TryIntegeriser for RedisIntegeriser<A, C>
+where
+    A: Clone + Eq + Hash + ToRedisArgs + FromRedisValue,
+    C: ConnectionLike,
+{
+    type Item = A;
+    type Error = RedisError;
+
+    fn try_integerise(&mut self, a: A) -> RedisResult<usize> {
+        self.integerise(a)
+    }
+
+    fn try_find_key(&mut self, a: &A) -> RedisResult<Option<usize>> {
+        self.find_key(a)
+    }
+}
@@ -0,0 +1,238 @@
+//! Probabilistic summaries of streams of values, and integerisers built on them.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use {HashIntegeriser, Integeriser};
+
+/// Count–min sketch, i.e. an approximate counter of values that never
+/// underestimates a count.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CountMinSketch<A: Hash> {
+    width: usize,
+    counts: Vec<u64>,
+    values: PhantomData<fn(&A)>,
+}
+
+impl<A: Hash> CountMinSketch<A> {
+    /// Constructs a sketch with `depth` rows of `width` counters each.
+    /// Counts are overestimated by at most `e / width` times the total count
+    /// with probability at least `1 - exp(-depth)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `depth` is zero.
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(width > 0 && depth > 0, "count–min sketch must not be empty");
+        CountMinSketch {
+            width,
+            counts: vec![0; width * depth],
+            values: PhantomData,
+        }
+    }
+
+    /// Index of the counter in `row` that corresponds to a value with hash `h`.
+    fn cell(&self, h: u64, row: usize) -> usize {
+        let step = h.rotate_left(32) | 1;
+        row * self.width + (h.wrapping_add((row as u64).wrapping_mul(step)) % self.width as u64) as usize
+    }
+
+    fn depth(&self) -> usize {
+        self.counts.len() / self.width
+    }
+
+    /// Counts one more occurrence of `a` and returns the new estimate of its count.
+    pub fn add(&mut self, a: &A) -> u64 {
+        let h = hash(a);
+        let mut estimate = u64::MAX;
+        for row in 0..self.depth() {
+            let cell = self.cell(h, row);
+            self.counts[cell] += 1;
+            estimate = estimate.min(self.counts[cell]);
+        }
+        estimate
+    }
+
+    /// Estimated number of occurrences of `a`.
+    pub fn estimate(&self, a: &A) -> u64 {
+        let h = hash(a);
+        (0..self.depth()).map(|row| self.counts[self.cell(h, row)]).min().unwrap_or(0)
+    }
+}
+
+fn hash<A: Hash>(a: &A) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    a.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Integeriser that only admits values once they have (approximately)
+/// occurred `threshold` times, so that the rare values of a heavy-tailed
+/// stream take up no space besides the fixed-size sketch.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::sketch::FrequencyGated;
+///
+/// let mut integeriser = FrequencyGated::new(HashIntegeriser::new(), 2, 1024, 4);
+///
+/// assert_eq!(integeriser.observe("the"), None);
+/// assert_eq!(integeriser.observe("cat"), None);
+/// assert_eq!(integeriser.observe("the"), Some(0));
+/// assert_eq!(integeriser.observe("the"), Some(0));
+///
+/// assert_eq!(integeriser.inner().size(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FrequencyGated<I: Integeriser>
+where
+    I::Item: Hash,
+{
+    inner: I,
+    sketch: CountMinSketch<I::Item>,
+    threshold: u64,
+}
+
+impl<I: Integeriser> FrequencyGated<I>
+where
+    I::Item: Hash,
+{
+    /// Wraps `inner`, admitting values to it after `threshold` occurrences as
+    /// counted by a sketch of the given dimensions, see `CountMinSketch::new`.
+    pub fn new(inner: I, threshold: u64, width: usize, depth: usize) -> Self {
+        FrequencyGated {
+            inner,
+            sketch: CountMinSketch::new(width, depth),
+            threshold,
+        }
+    }
+
+    /// Counts an occurrence of `a` and returns its integer if `a` has been
+    /// admitted to the inner integeriser, either now or earlier.
+    pub fn observe(&mut self, a: I::Item) -> Option<usize> {
+        if let Some(k) = self.inner.find_key(&a) {
+            return Some(k);
+        }
+        if self.sketch.add(&a) >= self.threshold {
+            Some(self.inner.integerise(a))
+        } else {
+            None
+        }
+    }
+
+    /// The inner integeriser, which contains the admitted values.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// The sketch that counts the values which have not been admitted yet.
+    pub fn sketch(&self) -> &CountMinSketch<I::Item> {
+        &self.sketch
+    }
+}
+
+/// HyperLogLog sketch, which estimates the number of distinct values with a
+/// standard error of about `1.04 / sqrt(2^precision)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HyperLogLog<A: Hash> {
+    precision: u32,
+    registers: Vec<u8>,
+    values: PhantomData<fn(&A)>,
+}
+
+impl<A: Hash> HyperLogLog<A> {
+    /// Constructs an empty sketch with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `4 <= precision <= 18`.
+    pub fn new(precision: u32) -> Self {
+        assert!((4..=18).contains(&precision), "precision must be between 4 and 18");
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+            values: PhantomData,
+        }
+    }
+
+    /// Adds `a` to the sketch.
+    pub fn insert(&mut self, a: &A) {
+        let h = hash(a);
+        let register = (h >> (64 - self.precision)) as usize;
+        let rank = ((h << self.precision).leading_zeros() + 1).min(64 - self.precision + 1) as u8;
+        if self.registers[register] < rank {
+            self.registers[register] = rank;
+        }
+    }
+
+    /// Estimated number of distinct values that have been added.
+    pub fn estimate(&self) -> usize {
+        let m = self.registers.len() as f64;
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        let raw = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // linear counting is more precise for small cardinalities
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            raw.round() as usize
+        }
+    }
+}
+
+/// Estimates the number of distinct values in `values` using a
+/// `HyperLogLog` sketch with `2^14` registers (standard error about 0.8%).
+///
+/// # Example
+///
+/// ```
+/// use integeriser::sketch::estimate_distinct;
+///
+/// let estimate = estimate_distinct((0..10_000).map(|i| i % 1000));
+/// assert!(estimate > 950 && estimate < 1050);
+/// ```
+pub fn estimate_distinct<A: Hash, It: IntoIterator<Item = A>>(values: It) -> usize {
+    let mut sketch = HyperLogLog::new(14);
+    for a in values {
+        sketch.insert(&a);
+    }
+    sketch.estimate()
+}
+
+/// Builds a `HashIntegeriser` from `values` in two passes: the first pass
+/// estimates the number of distinct values, from which the capacity of the
+/// integeriser is chosen, and the second pass integerises the values.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::Integeriser;
+/// use integeriser::sketch::build_presized;
+///
+/// let words = vec!["a", "rose", "is", "a", "rose"];
+/// let integeriser = build_presized(words.iter().cloned());
+///
+/// assert_eq!(integeriser.size(), 3);
+/// ```
+pub fn build_presized<A, It>(values: It) -> HashIntegeriser<A>
+where
+    A: Clone + Eq + Hash,
+    It: IntoIterator<Item = A> + Clone,
+{
+    // leave room for the estimation error of about three standard deviations
+    let capacity = estimate_distinct(values.clone()) / 40 * 41 + 16;
+    let mut integeriser = HashIntegeriser::with_capacity(capacity);
+    for a in values {
+        integeriser.integerise(a);
+    }
+    integeriser
+}
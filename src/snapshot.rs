@@ -0,0 +1,105 @@
+//! Evolving a vocabulary without renumbering the values that a previously
+//! saved version of it (and anything trained on that version) depends on.
+
+use std::error::Error;
+use std::fmt;
+
+use Integeriser;
+
+/// An id that a snapshot and the current integeriser assign to different
+/// values, or that one of them assigns to no value although it is below
+/// its size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict<A> {
+    /// The id in question.
+    pub id: usize,
+    /// The value of `id` in the snapshot, if any.
+    pub snapshot: Option<A>,
+    /// The value of `id` in the current integeriser, if any.
+    pub current: Option<A>,
+}
+
+impl<A: fmt::Debug> fmt::Display for Conflict<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "id {} is {} in the snapshot but {} in the current integeriser",
+            self.id,
+            describe(&self.snapshot),
+            describe(&self.current)
+        )
+    }
+}
+
+fn describe<A: fmt::Debug>(a: &Option<A>) -> String {
+    match *a {
+        Some(ref a) => format!("{:?}", a),
+        None => "missing".to_string(),
+    }
+}
+
+impl<A: fmt::Debug> Error for Conflict<A> {}
+
+/// Makes `current` an extension of `snapshot`, i.e. makes every id of
+/// `snapshot` refer to the same value in `current`.
+/// Values of `snapshot` whose ids are not yet used in `current` are
+/// integerised in the order of their ids, and their number is returned.
+/// Values that only `current` knows keep their ids.
+///
+/// If `current` and `snapshot` disagree on any id, all such conflicts are
+/// returned and `current` is left unchanged.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::snapshot::{extend_from_snapshot, Conflict};
+///
+/// let mut saved = HashIntegeriser::new();
+/// for w in &["a", "b", "c"] {
+///     saved.integerise(w.to_string());
+/// }
+///
+/// let mut current = HashIntegeriser::new();
+/// current.integerise("a".to_string());
+/// assert_eq!(extend_from_snapshot(&mut current, &saved), Ok(2));
+/// current.integerise("d".to_string());
+/// assert_eq!(extend_from_snapshot(&mut current, &saved), Ok(0));
+///
+/// let mut renumbered = HashIntegeriser::new();
+/// renumbered.integerise("b".to_string());
+/// let conflict = Conflict { id: 0, snapshot: Some("a".to_string()), current: Some("b".to_string()) };
+/// assert_eq!(extend_from_snapshot(&mut renumbered, &saved), Err(vec![conflict]));
+/// assert_eq!(renumbered.size(), 1);
+/// ```
+pub fn extend_from_snapshot<I, J, A>(current: &mut I, snapshot: &J) -> Result<usize, Vec<Conflict<A>>>
+where
+    I: Integeriser<Item = A>,
+    J: Integeriser<Item = A>,
+    A: Clone + Eq,
+{
+    trace_span!("extend_from_snapshot", values = snapshot.size());
+    let shared = current.size().min(snapshot.size());
+    let mut conflicts = Vec::new();
+    for k in 0..snapshot.size() {
+        let old = snapshot.find_value(k);
+        // ids beyond `current` only conflict if the snapshot has no value for them
+        let new = if k < shared { current.find_value(k) } else { old };
+        if old.is_none() || old != new {
+            conflicts.push(Conflict { id: k, snapshot: old.cloned(), current: new.cloned() });
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(conflicts);
+    }
+
+    // if a remaining value of the snapshot were already known to `current`,
+    // its id would be below `shared` and would have caused a conflict there
+
+    for k in shared..snapshot.size() {
+        if let Some(old) = snapshot.find_value(k) {
+            current.integerise(old.clone());
+        }
+    }
+    Ok(snapshot.size() - shared)
+}
@@ -0,0 +1,174 @@
+//! Integerisers whose ids may contain gaps, e.g. for vocabularies whose ids
+//! are dictated by an external system.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+/// Error for assigning an id that conflicts with the ids assigned so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IdConflict {
+    /// The id is already assigned to a different value.
+    IdTaken(usize),
+    /// The value is already assigned to the given, different id.
+    ValueTaken(usize),
+    /// No id after the given or the largest id would be left for `integerise`.
+    Overflow,
+}
+
+impl fmt::Display for IdConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IdConflict::IdTaken(k) => write!(f, "id {} is already assigned to a different value", k),
+            IdConflict::ValueTaken(k) => write!(f, "the value is already assigned to the id {}", k),
+            IdConflict::Overflow => write!(f, "the ids are exhausted"),
+        }
+    }
+}
+
+impl Error for IdConflict {}
+
+/// Integeriser whose ids need not be consecutive.
+/// Ids can be assigned explicitly with `insert_with_id`; `integerise`
+/// assigns new values the id after the largest id assigned so far.
+/// `size` is the number of values, which may be smaller than the largest id.
+///
+/// Since the ids may contain gaps, this structure does not implement
+/// `Integeriser`, but has methods of the same names.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::sparse::{IdConflict, SparseIntegeriser};
+///
+/// let mut codes = SparseIntegeriser::new();
+/// assert_eq!(codes.insert_with_id(100, "fever"), Ok(()));
+/// assert_eq!(codes.insert_with_id(42, "cough"), Ok(()));
+/// assert_eq!(codes.insert_with_id(42, "cough"), Ok(()));
+///
+/// assert_eq!(codes.insert_with_id(42, "rash"), Err(IdConflict::IdTaken(42)));
+/// assert_eq!(codes.insert_with_id(7, "fever"), Err(IdConflict::ValueTaken(100)));
+///
+/// assert_eq!(codes.integerise("rash"), 101);
+/// assert_eq!(codes.find_value(42), Some(&"cough"));
+/// assert_eq!(codes.find_value(43), None);
+/// assert_eq!(codes.size(), 3);
+/// assert_eq!(codes.iter().map(|(k, _)| k).collect::<Vec<_>>(), vec![42, 100, 101]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SparseIntegeriser<A: Eq + Hash, S: BuildHasher = RandomState> {
+    map: BTreeMap<usize, A>,
+    rmap: HashMap<A, usize, S>,
+    next: usize,
+}
+
+impl<A: Eq + Hash> SparseIntegeriser<A> {
+    /// Constructs a new, empty `SparseIntegeriser<A>`.
+    pub fn new() -> Self {
+        SparseIntegeriser { map: BTreeMap::new(), rmap: HashMap::new(), next: 0 }
+    }
+}
+
+impl<A: Eq + Hash, S: BuildHasher + Default> Default for SparseIntegeriser<A, S> {
+    fn default() -> Self {
+        SparseIntegeriser { map: BTreeMap::new(), rmap: HashMap::default(), next: 0 }
+    }
+}
+
+impl<A: Clone + Eq + Hash, S: BuildHasher> SparseIntegeriser<A, S> {
+    /// Assigns the id `k` to `a`.
+    /// Assigning a value the id that it already has is not a conflict.
+    pub fn insert_with_id(&mut self, k: usize, a: A) -> Result<(), IdConflict> {
+        match (self.map.get(&k), self.rmap.get(&a)) {
+            (_, Some(&j)) if j == k => Ok(()),
+            (_, Some(&j)) => Err(IdConflict::ValueTaken(j)),
+            (Some(_), None) => Err(IdConflict::IdTaken(k)),
+            (None, None) => {
+                let next = k.checked_add(1).ok_or(IdConflict::Overflow)?;
+                self.map.insert(k, a.clone());
+                self.rmap.insert(a, k);
+                self.next = self.next.max(next);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a unique integer for the given value `a`, i.e. its id or the
+    /// id after the largest id assigned so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is new and the next id would be `usize::MAX`, see `try_integerise`.
+    pub fn integerise(&mut self, a: A) -> usize {
+        match self.try_integerise(a) {
+            Ok(k) => k,
+            Err(_) => panic!("SparseIntegeriser has no ids left"),
+        }
+    }
+
+    /// Returns a unique integer for the given value `a`, see `integerise`,
+    /// or fails if the next id would be `usize::MAX`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use integeriser::sparse::{IdConflict, SparseIntegeriser};
+    ///
+    /// let mut integeriser = SparseIntegeriser::new();
+    /// assert_eq!(integeriser.insert_with_id(usize::MAX, "last"), Err(IdConflict::Overflow));
+    /// assert_eq!(integeriser.insert_with_id(usize::MAX - 1, "last"), Ok(()));
+    ///
+    /// assert_eq!(integeriser.try_integerise("first"), Err(IdConflict::Overflow));
+    /// assert_eq!(integeriser.try_integerise("last"), Ok(usize::MAX - 1));
+    /// ```
+    pub fn try_integerise(&mut self, a: A) -> Result<usize, IdConflict> {
+        if let Some(&k) = self.rmap.get(&a) {
+            return Ok(k);
+        }
+        let k = self.next;
+        self.next = k.checked_add(1).ok_or(IdConflict::Overflow)?;
+        self.map.insert(k, a.clone());
+        self.rmap.insert(a, k);
+        Ok(k)
+    }
+
+    /// Lookup the value that corresponds to the integer `k: usize`.
+    pub fn find_value(&self, k: usize) -> Option<&A> {
+        self.map.get(&k)
+    }
+
+    /// Lookup the integer that corresponds to the value `a: A`.
+    pub fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(a).cloned()
+    }
+
+    /// Number of values, which may be smaller than the largest id.
+    pub fn size(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Iterates over all pairs of id and value in ascending order of ids.
+    pub fn iter(&self) -> SparseIter<'_, A> {
+        SparseIter { inner: self.map.iter() }
+    }
+}
+
+/// Iterator over the pairs of id and value of a `SparseIntegeriser`.
+#[derive(Clone, Debug)]
+pub struct SparseIter<'a, A: 'a> {
+    inner: btree_map::Iter<'a, usize, A>,
+}
+
+impl<'a, A> Iterator for SparseIter<'a, A> {
+    type Item = (usize, &'a A);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(&k, a)| (k, a))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
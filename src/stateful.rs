@@ -0,0 +1,193 @@
+//! (De)serialising data that contains integers of an integeriser, such that
+//! the serialised form contains the corresponding values instead.
+//!
+//! Serialisation resolves every integer to its value, and deserialisation
+//! integerises every value again, possibly with a different integeriser.
+//! Custom types implement `SerializeState` and `DeserializeState` by
+//! delegating to the implementations for their fields.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+
+use Integeriser;
+
+/// Types that can be serialised with the help of an integeriser.
+pub trait SerializeState<I> {
+    /// Serialises `self`, replacing integers by the values of `integeriser`.
+    fn serialize_state<S: Serializer>(&self, integeriser: &I, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+/// Types that can be deserialised with the help of an integeriser.
+pub trait DeserializeState<'de, I>: Sized {
+    /// Deserialises a value, replacing values by their integers in `integeriser`.
+    fn deserialize_state<D: Deserializer<'de>>(integeriser: &mut I, deserializer: D) -> Result<Self, D::Error>;
+}
+
+/// `Serialize` implementation for a `SerializeState` together with its integeriser.
+///
+/// # Example
+///
+/// ```
+/// extern crate integeriser;
+/// extern crate serde;
+/// extern crate serde_json;
+///
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::stateful::{Resolved, Interned};
+/// use serde::de::DeserializeSeed;
+///
+/// # fn main() {
+/// let mut integeriser = HashIntegeriser::new();
+/// let sentence: Vec<usize> = "a rose is a rose".split(' ').map(|w| integeriser.integerise(w.to_string())).collect();
+///
+/// let json = serde_json::to_string(&Resolved::new(&sentence, &integeriser)).unwrap();
+/// assert_eq!(json, r#"["a","rose","is","a","rose"]"#);
+///
+/// let mut other = HashIntegeriser::new();
+/// other.integerise("is".to_string());
+/// let mut deserializer = serde_json::Deserializer::from_str(&json);
+/// let restored: Vec<usize> = Interned::new(&mut other).deserialize(&mut deserializer).unwrap();
+///
+/// assert_eq!(restored, vec![1, 2, 0, 1, 2]);
+/// # }
+/// ```
+pub struct Resolved<'a, T: 'a + ?Sized, I: 'a> {
+    data: &'a T,
+    integeriser: &'a I,
+}
+
+impl<'a, T: ?Sized, I> Resolved<'a, T, I> {
+    /// Pairs `data` with the integeriser that its integers refer to.
+    pub fn new(data: &'a T, integeriser: &'a I) -> Self {
+        Resolved { data, integeriser }
+    }
+}
+
+impl<'a, T: SerializeState<I> + ?Sized, I> Serialize for Resolved<'a, T, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data.serialize_state(self.integeriser, serializer)
+    }
+}
+
+/// `DeserializeSeed` implementation for a `DeserializeState`, see `Resolved`.
+pub struct Interned<'a, T, I: 'a> {
+    integeriser: &'a mut I,
+    data: PhantomData<fn() -> T>,
+}
+
+impl<'a, T, I> Interned<'a, T, I> {
+    /// Deserialises data whose values are integerised with `integeriser`.
+    pub fn new(integeriser: &'a mut I) -> Self {
+        Interned { integeriser, data: PhantomData }
+    }
+}
+
+impl<'a, 'de, T: DeserializeState<'de, I>, I> DeserializeSeed<'de> for Interned<'a, T, I> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T, D::Error> {
+        T::deserialize_state(self.integeriser, deserializer)
+    }
+}
+
+impl<I: Integeriser> SerializeState<I> for usize
+where
+    I::Item: Serialize,
+{
+    fn serialize_state<S: Serializer>(&self, integeriser: &I, serializer: S) -> Result<S::Ok, S::Error> {
+        match integeriser.find_value(*self) {
+            Some(a) => a.serialize(serializer),
+            None => Err(ser::Error::custom(format_args!("no value corresponds to the integer {}", self))),
+        }
+    }
+}
+
+impl<'de, I: Integeriser> DeserializeState<'de, I> for usize
+where
+    I::Item: Deserialize<'de>,
+{
+    fn deserialize_state<D: Deserializer<'de>>(integeriser: &mut I, deserializer: D) -> Result<usize, D::Error> {
+        Ok(integeriser.integerise(I::Item::deserialize(deserializer)?))
+    }
+}
+
+impl<I, T: SerializeState<I>> SerializeState<I> for [T] {
+    fn serialize_state<S: Serializer>(&self, integeriser: &I, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for t in self {
+            seq.serialize_element(&Resolved::new(t, integeriser))?;
+        }
+        seq.end()
+    }
+}
+
+impl<I, T: SerializeState<I>> SerializeState<I> for Vec<T> {
+    fn serialize_state<S: Serializer>(&self, integeriser: &I, serializer: S) -> Result<S::Ok, S::Error> {
+        self[..].serialize_state(integeriser, serializer)
+    }
+}
+
+impl<'de, I, T: DeserializeState<'de, I>> DeserializeState<'de, I> for Vec<T> {
+    fn deserialize_state<D: Deserializer<'de>>(integeriser: &mut I, deserializer: D) -> Result<Vec<T>, D::Error> {
+        struct SeqVisitor<'a, T, I: 'a>(&'a mut I, PhantomData<fn() -> T>);
+
+        impl<'a, 'de, T: DeserializeState<'de, I>, I> Visitor<'de> for SeqVisitor<'a, T, I> {
+            type Value = Vec<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+                let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(t) = seq.next_element_seed(Interned::new(&mut *self.0))? {
+                    result.push(t);
+                }
+                Ok(result)
+            }
+        }
+
+        deserializer.deserialize_seq(SeqVisitor(integeriser, PhantomData))
+    }
+}
+
+impl<I, T: SerializeState<I>> SerializeState<I> for Option<T> {
+    fn serialize_state<S: Serializer>(&self, integeriser: &I, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Some(ref t) => serializer.serialize_some(&Resolved::new(t, integeriser)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de, I, T: DeserializeState<'de, I>> DeserializeState<'de, I> for Option<T> {
+    fn deserialize_state<D: Deserializer<'de>>(integeriser: &mut I, deserializer: D) -> Result<Option<T>, D::Error> {
+        struct OptionVisitor<'a, T, I: 'a>(&'a mut I, PhantomData<fn() -> T>);
+
+        impl<'a, 'de, T: DeserializeState<'de, I>, I> Visitor<'de> for OptionVisitor<'a, T, I> {
+            type Value = Option<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an optional value")
+            }
+
+            fn visit_none<E: de::Error>(self) -> Result<Option<T>, E> {
+                Ok(None)
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Option<T>, E> {
+                Ok(None)
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<T>, D::Error> {
+                T::deserialize_state(self.0, deserializer).map(Some)
+            }
+        }
+
+        deserializer.deserialize_option(OptionVisitor(integeriser, PhantomData))
+    }
+}
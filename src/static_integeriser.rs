@@ -0,0 +1,204 @@
+//! Integerisers of string constants whose lookup tables are computed at
+//! compile time, see `static_integeriser!`.
+
+use Integeriser;
+
+const EMPTY: usize = usize::MAX;
+
+/// Integeriser of a fixed set of strings, which are integerised in the
+/// given order.  The strings and a hash table for looking them up are
+/// computed at compile time by `static_integeriser!` and embedded in the
+/// binary, so a `StaticIntegeriser` never allocates.
+///
+/// The hash table is not a perfect hash: it uses open addressing with
+/// linear probing over FNV-1a hashes, and at most half of its slots are
+/// occupied, so a lookup compares with only a few strings on average.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate integeriser;
+///
+/// use integeriser::Integeriser;
+/// use integeriser::static_integeriser::StaticIntegeriser;
+///
+/// static KEYWORDS: StaticIntegeriser = static_integeriser!["if", "then", "else"];
+///
+/// # fn main() {
+/// assert_eq!(KEYWORDS.find_key_borrowed("then"), Some(1));
+/// assert_eq!(KEYWORDS.find_key_borrowed("fi"), None);
+/// assert_eq!(KEYWORDS.find_value(2), Some(&"else"));
+/// assert_eq!(KEYWORDS.size(), 3);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct StaticIntegeriser {
+    values: &'static [&'static str],
+    table: &'static [usize],
+}
+
+/// Generates a `StaticIntegeriser` of the given strings at compile time.
+/// The expansion is a constant expression and can initialise a `static` or `const`.
+/// Compilation fails if a string is given twice:
+///
+/// ```compile_fail,E0080
+/// #[macro_use]
+/// extern crate integeriser;
+///
+/// static KEYWORDS: integeriser::static_integeriser::StaticIntegeriser = static_integeriser!["if", "if"];
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! static_integeriser {
+    ($($value:expr),* $(,)*) => {{
+        const VALUES: &[&str] = &[$($value),*];
+        const TABLE: [usize; $crate::static_integeriser::table_len(VALUES.len())] =
+            $crate::static_integeriser::build_table(VALUES);
+        $crate::static_integeriser::StaticIntegeriser::from_parts(VALUES, &TABLE)
+    }};
+}
+
+const fn hash(s: &str) -> usize {
+    // FNV-1a
+    let bytes = s.as_bytes();
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        h ^= bytes[i] as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    h as usize
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[doc(hidden)]
+pub const fn table_len(n: usize) -> usize {
+    // a load factor of at most 1/2 keeps the probe sequences short
+    (2 * n).next_power_of_two()
+}
+
+/// Hash table of `values` with `LEN` slots, which has to be a power of two.
+/// At least one slot has to stay empty, or lookups of other strings would
+/// never end:
+///
+/// ```compile_fail,E0080
+/// use integeriser::static_integeriser::build_table;
+///
+/// const TABLE: [usize; 2] = build_table(&["if", "then"]);
+/// # fn main() { let _ = TABLE; }
+/// ```
+#[doc(hidden)]
+pub const fn build_table<const LEN: usize>(values: &[&str]) -> [usize; LEN] {
+    assert!(LEN.is_power_of_two(), "the table of the static integeriser has to have a power of two slots");
+    assert!(values.len() < LEN, "the table of the static integeriser is full");
+    let mut table = [EMPTY; LEN];
+    let mut k = 0;
+    while k < values.len() {
+        let mut slot = hash(values[k]) & (LEN - 1);
+        while table[slot] != EMPTY {
+            if str_eq(values[table[slot]], values[k]) {
+                panic!("a value of the static integeriser is given twice");
+            }
+            slot = (slot + 1) & (LEN - 1);
+        }
+        table[slot] = k;
+        k += 1;
+    }
+    table
+}
+
+impl StaticIntegeriser {
+    #[doc(hidden)]
+    pub const fn from_parts(values: &'static [&'static str], table: &'static [usize]) -> Self {
+        StaticIntegeriser { values, table }
+    }
+
+    /// Lookup the integer that corresponds to the string `s`.
+    pub fn find_key_borrowed(&self, s: &str) -> Option<usize> {
+        let mask = self.table.len() - 1;
+        let mut slot = hash(s) & mask;
+        loop {
+            match self.table[slot] {
+                EMPTY => return None,
+                k if self.values[k] == s => return Some(k),
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    /// All strings, in the order of their integers.
+    pub fn values(&self) -> &'static [&'static str] {
+        self.values
+    }
+}
+
+impl Integeriser for StaticIntegeriser {
+    type Item = &'static str;
+
+    /// Returns the integer of `a`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is not one of the strings of `self`, as no strings can
+    /// be added to a `StaticIntegeriser`.
+    fn integerise(&mut self, a: &'static str) -> usize {
+        match self.find_key_borrowed(a) {
+            Some(k) => k,
+            None => panic!("{:?} is not a value of the static integeriser", a),
+        }
+    }
+
+    fn find_value(&self, k: usize) -> Option<&&'static str> {
+        self.values.get(k)
+    }
+
+    fn find_key(&self, a: &&'static str) -> Option<usize> {
+        self.find_key_borrowed(a)
+    }
+
+    fn size(&self) -> usize {
+        self.values.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_table, table_len, StaticIntegeriser, EMPTY};
+
+    static EMPTY_INTEGERISER: StaticIntegeriser = static_integeriser![];
+    // one string fewer than slots is the fullest table that is accepted
+    const FULL: [usize; 4] = build_table(&["a", "b", "c"]);
+
+    #[test]
+    fn empty_integerisers_find_nothing() {
+        assert_eq!(table_len(0), 1);
+        assert_eq!(EMPTY_INTEGERISER.find_key_borrowed(""), None);
+        assert!(EMPTY_INTEGERISER.values().is_empty());
+    }
+
+    #[test]
+    fn nearly_full_tables_end_lookups() {
+        assert_eq!(FULL.iter().filter(|&&k| k == EMPTY).count(), 1);
+        let integeriser = StaticIntegeriser::from_parts(&["a", "b", "c"], &FULL);
+        for (k, s) in ["a", "b", "c"].iter().enumerate() {
+            assert_eq!(integeriser.find_key_borrowed(s), Some(k));
+        }
+        assert_eq!(integeriser.find_key_borrowed("d"), None);
+    }
+}
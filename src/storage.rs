@@ -0,0 +1,495 @@
+//! Containers in which `HashIntegeriser` keeps its values.
+//!
+//! The backends are `Vec`, `BoxedStorage`, `ChunkedStorage` and
+//! `ArenaStorage`.  `ArenaStorage` holds unsized values as bytes and is
+//! used through `arena::ArenaIntegeriser`, which is a `HashIntegeriser` with
+//! this storage that converts between `&T` and the `ArenaRef<T>` it stores.
+//!
+//! There is no memory-mapped backend: `Storage::get` hands out `&A` for
+//! arbitrary values, while a mapped file only holds bytes, so such a
+//! backend would need a byte representation of `A` and a file format of its
+//! own.  Vocabularies are written to and read from files with `persist`.
+
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::Flatten;
+use std::marker::PhantomData;
+use std::{slice, vec};
+
+use arena::ArenaValue;
+use {HashIntegeriser, Integeriser};
+
+/// Sequence of values that can only grow at its end.
+pub trait Storage<A> {
+    /// Appends `a`, which will be available at index `self.len()`.
+    fn push(&mut self, a: A);
+
+    /// The value at index `k`, if there is one.
+    fn get(&self, k: usize) -> Option<&A>;
+
+    /// Number of stored values.
+    fn len(&self) -> usize;
+
+    /// Number of values that can be stored without reallocating.
+    fn capacity(&self) -> usize {
+        self.len()
+    }
+
+    /// Whether no values are stored.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Storage in which values never move while it grows, so that references
+/// to them remain valid across `push`.
+///
+/// # Safety
+///
+/// `push` must neither move nor drop the values stored before.
+pub unsafe trait StableStorage<A>: Storage<A> {}
+
+impl<A> Storage<A> for Vec<A> {
+    fn push(&mut self, a: A) {
+        Vec::push(self, a)
+    }
+
+    fn get(&self, k: usize) -> Option<&A> {
+        <[A]>::get(self, k)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+}
+
+/// Storage that keeps every value in its own heap allocation, so that
+/// values never move, and growing the storage only moves pointers.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::hash_map::RandomState;
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::storage::BoxedStorage;
+///
+/// let mut integeriser: HashIntegeriser<String, RandomState, BoxedStorage<String>> = HashIntegeriser::default();
+/// let k = integeriser.integerise("a".to_string());
+///
+/// assert_eq!(integeriser.find_value(k), Some(&"a".to_string()));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoxedStorage<A> {
+    values: Vec<Box<A>>,
+}
+
+impl<A> Default for BoxedStorage<A> {
+    fn default() -> Self {
+        BoxedStorage { values: Vec::new() }
+    }
+}
+
+impl<A> Storage<A> for BoxedStorage<A> {
+    fn push(&mut self, a: A) {
+        self.values.push(Box::new(a))
+    }
+
+    fn get(&self, k: usize) -> Option<&A> {
+        self.values.get(k).map(|a| &**a)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+}
+
+// the values live in their own allocations, the vector only moves the boxes
+unsafe impl<A> StableStorage<A> for BoxedStorage<A> {}
+
+impl<A> IntoIterator for BoxedStorage<A> {
+    type Item = A;
+    type IntoIter = BoxedIntoIter<A>;
+
+    fn into_iter(self) -> BoxedIntoIter<A> {
+        BoxedIntoIter(self.values.into_iter())
+    }
+}
+
+/// Iterator that moves the values out of a `BoxedStorage`.
+#[derive(Debug)]
+pub struct BoxedIntoIter<A>(vec::IntoIter<Box<A>>);
+
+impl<A> Iterator for BoxedIntoIter<A> {
+    type Item = A;
+
+    fn next(&mut self) -> Option<A> {
+        self.0.next().map(|a| *a)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+/// Storage that keeps values in chunks of doubling size which are never
+/// reallocated, so that values never move and growing the storage never
+/// copies values.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::storage::{ChunkedStorage, Storage};
+///
+/// let mut storage = ChunkedStorage::default();
+/// for i in 0..100 {
+///     storage.push(i);
+/// }
+///
+/// assert_eq!(storage.len(), 100);
+/// assert_eq!(storage.get(57), Some(&57));
+/// assert_eq!(storage.get(100), None);
+/// assert_eq!(storage.into_iter().sum::<i32>(), 4950);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkedStorage<A> {
+    chunks: Vec<Vec<A>>,
+    len: usize,
+}
+
+impl<A> Default for ChunkedStorage<A> {
+    fn default() -> Self {
+        ChunkedStorage { chunks: Vec::new(), len: 0 }
+    }
+}
+
+/// Capacity of the first chunk of a `ChunkedStorage`.
+const FIRST_CHUNK: usize = 16;
+
+impl<A> ChunkedStorage<A> {
+    /// Chunk and offset of the value at index `k`.
+    fn locate(k: usize) -> (usize, usize) {
+        // chunk `c` starts at index `FIRST_CHUNK * (2^c - 1)`
+        let c = (k / FIRST_CHUNK + 1).ilog2() as usize;
+        (c, k - FIRST_CHUNK * ((1 << c) - 1))
+    }
+}
+
+impl<A> Storage<A> for ChunkedStorage<A> {
+    fn push(&mut self, a: A) {
+        let (c, _) = ChunkedStorage::<A>::locate(self.len);
+        if c == self.chunks.len() {
+            self.chunks.push(Vec::with_capacity(FIRST_CHUNK << c));
+        }
+        self.chunks[c].push(a);
+        self.len += 1;
+    }
+
+    fn get(&self, k: usize) -> Option<&A> {
+        if k < self.len {
+            let (c, i) = ChunkedStorage::<A>::locate(k);
+            self.chunks[c].get(i)
+        } else {
+            None
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn capacity(&self) -> usize {
+        self.chunks.iter().map(Vec::capacity).sum()
+    }
+}
+
+// chunks are allocated with their final capacity and never reallocated
+unsafe impl<A> StableStorage<A> for ChunkedStorage<A> {}
+
+impl<A> IntoIterator for ChunkedStorage<A> {
+    type Item = A;
+    type IntoIter = Flatten<vec::IntoIter<Vec<A>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter().flatten()
+    }
+}
+
+/// Reference to a value of the unsized type `T` in an `ArenaStorage`.
+/// Values are compared and hashed by their bytes.
+///
+/// References can only be obtained from the storage that holds their value,
+/// and they cannot be cloned, so they never outlive it.
+pub struct ArenaRef<T: ?Sized + ArenaValue> {
+    ptr: *const u8,
+    len: usize,
+    values: PhantomData<fn(&T) -> &T>,
+}
+
+// a reference behaves like a `&[u8]` into its storage
+unsafe impl<T: ?Sized + ArenaValue> Send for ArenaRef<T> {}
+unsafe impl<T: ?Sized + ArenaValue> Sync for ArenaRef<T> {}
+
+impl<T: ?Sized + ArenaValue> ArenaRef<T> {
+    fn bytes(&self) -> &[u8] {
+        // the bytes live in a block of the storage that is never reallocated
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// The referenced value.
+    pub fn value(&self) -> &T {
+        // the bytes have been obtained from `as_bytes`
+        unsafe { T::from_bytes(self.bytes()) }
+    }
+}
+
+impl<T: ?Sized + ArenaValue> PartialEq for ArenaRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes() == other.bytes()
+    }
+}
+
+impl<T: ?Sized + ArenaValue> Eq for ArenaRef<T> {}
+
+impl<T: ?Sized + ArenaValue> Hash for ArenaRef<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes().hash(state)
+    }
+}
+
+impl<T: ?Sized + ArenaValue> Borrow<[u8]> for ArenaRef<T> {
+    fn borrow(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+impl<T: ?Sized + ArenaValue + fmt::Debug> fmt::Debug for ArenaRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value().fmt(f)
+    }
+}
+
+/// Storage that copies values of the unsized type `T` (such as `[u8]` or
+/// `str`) back to back into blocks of bytes which are never reallocated, so
+/// that storing a value allocates nothing but (amortised) room for its bytes.
+/// It backs `arena::ArenaIntegeriser`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::arena::ArenaIntegeriser;
+/// use integeriser::storage::{ArenaRef, Storage};
+///
+/// let mut integeriser: ArenaIntegeriser<str> = ArenaIntegeriser::new();
+/// integeriser.integerise("cat");
+/// integeriser.integerise("dog");
+///
+/// let storage = integeriser.values();
+/// assert_eq!(storage.len(), 2);
+/// assert_eq!(storage.get(1).map(ArenaRef::value), Some("dog"));
+/// assert_eq!(storage.bytes(), 6);
+/// ```
+pub struct ArenaStorage<T: ?Sized + ArenaValue> {
+    blocks: Vec<Vec<u8>>,
+    values: Vec<ArenaRef<T>>,
+}
+
+/// Capacity of the first block of an `ArenaStorage`.
+const FIRST_BLOCK: usize = 1024;
+
+impl<T: ?Sized + ArenaValue> ArenaStorage<T> {
+    /// Copies the bytes of a value into the last block, or into a new block
+    /// if they do not fit, and returns a reference to them.
+    fn alloc_bytes(&mut self, bytes: &[u8]) -> ArenaRef<T> {
+        let fits = match self.blocks.last() {
+            Some(block) => block.capacity() - block.len() >= bytes.len(),
+            None => false,
+        };
+        if !fits {
+            let capacity = self.blocks.last().map_or(FIRST_BLOCK, |block| 2 * block.capacity());
+            self.blocks.push(Vec::with_capacity(capacity.max(bytes.len())));
+        }
+        let block = self.blocks.last_mut().expect("there is a block");
+        let start = block.len();
+        // the block does not grow beyond its capacity, so it never moves
+        block.extend_from_slice(bytes);
+        ArenaRef { ptr: block[start..].as_ptr(), len: bytes.len(), values: PhantomData }
+    }
+
+    /// Stores a copy of `a` and returns a second reference to it.
+    pub(crate) fn alloc(&mut self, a: &T) -> ArenaRef<T> {
+        let r = self.alloc_bytes(a.as_bytes());
+        self.values.push(ArenaRef { ptr: r.ptr, len: r.len, values: PhantomData });
+        r
+    }
+
+    /// Number of bytes of all stored values.
+    pub fn bytes(&self) -> usize {
+        self.blocks.iter().map(Vec::len).sum()
+    }
+}
+
+impl<T: ?Sized + ArenaValue> Default for ArenaStorage<T> {
+    fn default() -> Self {
+        ArenaStorage { blocks: Vec::new(), values: Vec::new() }
+    }
+}
+
+impl<T: ?Sized + ArenaValue> Storage<ArenaRef<T>> for ArenaStorage<T> {
+    fn push(&mut self, a: ArenaRef<T>) {
+        self.alloc(a.value());
+    }
+
+    fn get(&self, k: usize) -> Option<&ArenaRef<T>> {
+        self.values.get(k)
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+}
+
+impl<T: ?Sized + ArenaValue + fmt::Debug> fmt::Debug for ArenaStorage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(&self.values).finish()
+    }
+}
+
+/// View of a `HashIntegeriser` with a `StableStorage` that can only add
+/// values, see `HashIntegeriser::grow_only`.
+/// Values are added through a shared reference, and the references
+/// returned by `find_value` remain valid as long as the view exists.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::hash_map::RandomState;
+/// use integeriser::HashIntegeriser;
+/// use integeriser::storage::ChunkedStorage;
+///
+/// let mut integeriser: HashIntegeriser<String, RandomState, ChunkedStorage<String>> = HashIntegeriser::default();
+/// let view = integeriser.grow_only();
+///
+/// let k = view.integerise("first".to_string());
+/// let first: &String = view.find_value(k).unwrap();
+/// for i in 0..1000 {
+///     view.integerise(i.to_string());
+/// }
+///
+/// assert_eq!(first, "first");
+/// assert_eq!(view.size(), 1001);
+/// ```
+#[derive(Debug)]
+pub struct GrowOnly<'a, A: 'a + Eq + Hash, S: 'a + BuildHasher, V: 'a> {
+    inner: RefCell<&'a mut HashIntegeriser<A, S, V>>,
+}
+
+impl<'a, A: Eq + Hash, S: BuildHasher, V: StableStorage<A>> GrowOnly<'a, A, S, V> {
+    pub(crate) fn new(inner: &'a mut HashIntegeriser<A, S, V>) -> Self {
+        GrowOnly { inner: RefCell::new(inner) }
+    }
+
+    /// Returns a unique integer for the given value `a`, see `Integeriser::integerise`.
+    pub fn integerise(&self, a: A) -> usize
+    where
+        A: Clone,
+    {
+        self.inner.borrow_mut().integerise(a)
+    }
+
+    /// Lookup the value that corresponds to the integer `k`.
+    pub fn find_value(&self, k: usize) -> Option<&'a A> {
+        let inner = self.inner.borrow();
+        // the view only adds values, which never moves the stored values
+        // because `V: StableStorage<A>`
+        inner.map.get(k).map(|a| unsafe { &*(a as *const A) })
+    }
+
+    /// Lookup the integer that corresponds to the value `a`.
+    pub fn find_key(&self, a: &A) -> Option<usize> {
+        self.inner.borrow().rmap.get(a).cloned()
+    }
+
+    /// Number of distinct values that are stored in the integeriser.
+    pub fn size(&self) -> usize {
+        self.inner.borrow().map.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+
+    use super::{BoxedStorage, ChunkedStorage, Storage, FIRST_CHUNK};
+    use HashIntegeriser;
+
+    #[test]
+    fn chunks_are_addressed_across_their_boundaries() {
+        assert_eq!(ChunkedStorage::<u8>::locate(0), (0, 0));
+        assert_eq!(ChunkedStorage::<u8>::locate(FIRST_CHUNK - 1), (0, FIRST_CHUNK - 1));
+        assert_eq!(ChunkedStorage::<u8>::locate(FIRST_CHUNK), (1, 0));
+        assert_eq!(ChunkedStorage::<u8>::locate(3 * FIRST_CHUNK - 1), (1, 2 * FIRST_CHUNK - 1));
+        assert_eq!(ChunkedStorage::<u8>::locate(3 * FIRST_CHUNK), (2, 0));
+
+        let mut storage = ChunkedStorage::default();
+        for i in 0..3 * FIRST_CHUNK + 1 {
+            storage.push(i);
+        }
+        assert_eq!(storage.chunks.len(), 3);
+        assert_eq!(storage.capacity(), 7 * FIRST_CHUNK);
+        for i in 0..3 * FIRST_CHUNK + 1 {
+            assert_eq!(storage.get(i), Some(&i));
+        }
+        assert_eq!(storage.get(3 * FIRST_CHUNK + 1), None);
+    }
+
+    #[test]
+    fn references_survive_new_chunks() {
+        let mut integeriser: HashIntegeriser<String, RandomState, ChunkedStorage<String>> = HashIntegeriser::default();
+        let view = integeriser.grow_only();
+        let values: Vec<&String> = (0..FIRST_CHUNK)
+            .map(|i| view.integerise(i.to_string()))
+            .map(|k| view.find_value(k).unwrap())
+            .collect();
+        let last = values[FIRST_CHUNK - 1] as *const String;
+
+        // the next value starts the second chunk
+        let k = view.integerise(FIRST_CHUNK.to_string());
+        assert_eq!(k, FIRST_CHUNK);
+        for i in FIRST_CHUNK + 1..10 * FIRST_CHUNK {
+            view.integerise(i.to_string());
+        }
+
+        assert_eq!(view.find_value(FIRST_CHUNK - 1).unwrap() as *const String, last);
+        for (i, a) in values.into_iter().enumerate() {
+            assert_eq!(*a, i.to_string());
+        }
+        assert_eq!(view.find_value(k), Some(&FIRST_CHUNK.to_string()));
+    }
+
+    #[test]
+    fn boxed_references_survive_reallocation() {
+        let mut integeriser: HashIntegeriser<String, RandomState, BoxedStorage<String>> = HashIntegeriser::default();
+        let view = integeriser.grow_only();
+        let k = view.integerise("first".to_string());
+        let first = view.find_value(k).unwrap();
+        for i in 0..1000 {
+            view.integerise(i.to_string());
+        }
+        assert_eq!(first, "first");
+        assert_eq!(view.size(), 1001);
+    }
+}
@@ -0,0 +1,184 @@
+//! Encoding streams of tokens as streams of variable-length integers.
+//!
+//! Every integer is written in LEB128, i.e. in groups of seven bits, least
+//! significant group first, where the high bit of each byte tells whether
+//! another byte follows.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::str;
+
+use Integeriser;
+
+/// Errors that can occur while encoding a stream of tokens.
+#[derive(Debug)]
+pub enum StreamError {
+    /// The underlying reader or writer failed.
+    Io(io::Error),
+    /// The token is not known to the integeriser.
+    UnknownToken(String),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StreamError::Io(ref e) => write!(f, "i/o error: {}", e),
+            StreamError::UnknownToken(ref t) => write!(f, "unknown token {:?}", t),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            StreamError::Io(ref e) => Some(e),
+            StreamError::UnknownToken(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(e: io::Error) -> Self {
+        StreamError::Io(e)
+    }
+}
+
+/// Writes `k` as a variable-length integer.
+pub fn write_varint<W: Write>(mut writer: W, mut k: usize) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        buf[len] = (k & 0x7f) as u8;
+        k >>= 7;
+        len += 1;
+        if k == 0 {
+            break;
+        }
+        buf[len - 1] |= 0x80;
+    }
+    writer.write_all(&buf[..len])
+}
+
+/// Reads a variable-length integer.
+/// Returns `None` if the reader is exhausted before the first byte.
+pub fn read_varint<R: Read>(mut reader: R) -> io::Result<Option<usize>> {
+    let mut k = 0usize;
+    let mut shift = 0u32;
+    let mut byte = [0];
+    loop {
+        if reader.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated integer"))
+            };
+        }
+        if shift >= usize::BITS || (shift > 0 && (byte[0] & 0x7f) as usize > usize::MAX >> shift) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "integer does not fit into usize"));
+        }
+        k |= ((byte[0] & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(k));
+        }
+    }
+}
+
+/// Splits `text` into tokens at Unicode whitespace, see `str::split_whitespace`.
+/// This is the tokenisation of `encode` and of the `integerise` binary.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::stream::tokens;
+///
+/// assert_eq!(tokens("a\u{a0}b  c\u{3000}d\n").collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+/// ```
+pub fn tokens(text: &str) -> str::SplitWhitespace<'_> {
+    text.split_whitespace()
+}
+
+/// Reads tokens (see `tokens`) from `reader` and writes their
+/// integers as variable-length integers to `writer`, returning the number
+/// of tokens.  Only the text between two ASCII whitespace characters is
+/// held in memory at a time.
+///
+/// If `update` is `true`, unknown tokens are integerised; otherwise, they
+/// cause an error.  Everything up to the erroneous token is already written.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::stream::{encode, read_varint, StreamError};
+///
+/// let mut vocab = HashIntegeriser::new();
+/// let mut encoded = Vec::new();
+/// let text = "a b\na\u{a0}c";
+/// assert_eq!(encode(text.as_bytes(), &mut encoded, &mut vocab, true).unwrap(), 4);
+///
+/// let mut reader = &encoded[..];
+/// let mut ks = Vec::new();
+/// while let Some(k) = read_varint(&mut reader).unwrap() {
+///     ks.push(k);
+/// }
+/// assert_eq!(ks, vec![0, 1, 0, 2]);
+///
+/// match encode("a d".as_bytes(), &mut encoded, &mut vocab, false) {
+///     Err(StreamError::UnknownToken(t)) => assert_eq!(t, "d"),
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn encode<R, W, I>(mut reader: R, mut writer: W, integeriser: &mut I, update: bool) -> Result<usize, StreamError>
+where
+    R: BufRead,
+    W: Write,
+    I: Integeriser<Item = String>,
+{
+    let mut bytes = Vec::new();
+    let mut token = String::new();
+    let mut count = 0;
+
+    loop {
+        // number of consumed bytes and whether the current chunk between
+        // ASCII whitespace is complete
+        let (used, complete) = {
+            let buf = reader.fill_buf()?;
+            match buf.iter().position(u8::is_ascii_whitespace) {
+                Some(i) => {
+                    bytes.extend_from_slice(&buf[..i]);
+                    (i + 1, true)
+                }
+                None => {
+                    bytes.extend_from_slice(buf);
+                    (buf.len(), buf.is_empty())
+                }
+            }
+        };
+        reader.consume(used);
+        if !complete {
+            continue;
+        }
+        let eof = used == 0;
+
+        // ASCII whitespace is whitespace in Unicode, too, so splitting the
+        // chunk further yields the same tokens as `tokens` on the whole text
+        let chunk = str::from_utf8(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        for t in tokens(chunk) {
+            token.clear();
+            token.push_str(t);
+            let k = match integeriser.find_key(&token) {
+                Some(k) => k,
+                None if update => integeriser.integerise(token.clone()),
+                None => return Err(StreamError::UnknownToken(token)),
+            };
+            write_varint(&mut writer, k)?;
+            count += 1;
+        }
+        bytes.clear();
+        if eof {
+            return Ok(count);
+        }
+    }
+}
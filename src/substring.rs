@@ -0,0 +1,139 @@
+//! Substring search across the strings stored in an integeriser.
+
+use Integeriser;
+
+/// Suffix array over the concatenated values of a string integeriser.
+/// The suffix array is a snapshot: values that are integerised after its
+/// construction are not known to it.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::substring::SubstringIndex;
+///
+/// let mut vocab = HashIntegeriser::new();
+/// for w in &["organization", "size", "realization", "real"] {
+///     vocab.integerise(w.to_string());
+/// }
+///
+/// let index = SubstringIndex::new(&vocab);
+///
+/// assert_eq!(index.ids_containing("ization"), vec![0, 2]);
+/// assert_eq!(index.ids_containing("real"), vec![2, 3]);
+/// assert!(index.ids_containing("zed").is_empty());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SubstringIndex {
+    text: Vec<u8>,
+    /// `bounds[k]` is the offset of the value with integer `k` in `text`;
+    /// the last entry is the length of `text`.
+    bounds: Vec<usize>,
+    /// Offsets of all suffixes of values, sorted by the remainder of their value.
+    suffixes: Vec<usize>,
+}
+
+impl SubstringIndex {
+    /// Builds the suffix array over all values currently stored in `integeriser`.
+    pub fn new<I, S>(integeriser: &I) -> Self
+    where
+        I: Integeriser<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut text = Vec::new();
+        let mut bounds = Vec::with_capacity(integeriser.size() + 1);
+        for k in 0..integeriser.size() {
+            bounds.push(text.len());
+            if let Some(value) = integeriser.find_value(k) {
+                text.extend_from_slice(value.as_ref().as_bytes());
+            }
+        }
+        bounds.push(text.len());
+
+        let mut index = SubstringIndex { text, bounds, suffixes: Vec::new() };
+        let mut suffixes: Vec<usize> = (0..index.text.len()).collect();
+        suffixes.sort_by(|&a, &b| index.suffix(a).cmp(index.suffix(b)));
+        index.suffixes = suffixes;
+        index
+    }
+
+    /// Integer of the value that contains the byte at offset `pos` of `text`.
+    fn value_at(&self, pos: usize) -> usize {
+        self.bounds.partition_point(|&start| start <= pos) - 1
+    }
+
+    /// The part of the value that starts at offset `pos` of `text`.
+    fn suffix(&self, pos: usize) -> &[u8] {
+        &self.text[pos..self.bounds[self.value_at(pos) + 1]]
+    }
+
+    /// Returns the integers (in ascending order) of all values that contain `s`.
+    pub fn ids_containing(&self, s: &str) -> Vec<usize> {
+        if s.is_empty() {
+            return (0..self.bounds.len().saturating_sub(1)).collect();
+        }
+
+        let s = s.as_bytes();
+        let from = self.suffixes.partition_point(|&pos| self.suffix(pos) < s);
+        let to = from + self.suffixes[from..].partition_point(|&pos| self.suffix(pos).starts_with(s));
+
+        let mut ids: Vec<usize> = self.suffixes[from..to].iter().map(|&pos| self.value_at(pos)).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubstringIndex;
+    use {HashIntegeriser, Integeriser};
+
+    fn index(values: &[&str]) -> SubstringIndex {
+        let mut integeriser = HashIntegeriser::new();
+        for a in values {
+            integeriser.integerise(a.to_string());
+        }
+        SubstringIndex::new(&integeriser)
+    }
+
+    #[test]
+    fn matches_do_not_cross_values() {
+        let index = index(&["ab", "cd", "abcd"]);
+        assert_eq!(index.ids_containing("bc"), vec![2]);
+        assert_eq!(index.ids_containing("dab"), Vec::<usize>::new());
+        assert_eq!(index.ids_containing("abcde"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn matches_at_the_ends_of_the_text_are_found() {
+        let index = index(&["xa", "b", "cz"]);
+        assert_eq!(index.ids_containing("x"), vec![0]);
+        assert_eq!(index.ids_containing("z"), vec![2]);
+        assert_eq!(index.ids_containing("cz"), vec![2]);
+        assert_eq!(index.ids_containing("b"), vec![1]);
+    }
+
+    #[test]
+    fn empty_values_and_patterns() {
+        let index = index(&["", "a", "b"]);
+        assert_eq!(index.ids_containing(""), vec![0, 1, 2]);
+        assert_eq!(index.ids_containing("a"), vec![1]);
+        assert_eq!(SubstringIndex::default().ids_containing(""), Vec::<usize>::new());
+        assert_eq!(SubstringIndex::default().ids_containing("a"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn repeated_matches_are_reported_once() {
+        let index = index(&["aaaa", "aa", "a"]);
+        assert_eq!(index.ids_containing("aa"), vec![0, 1]);
+        assert_eq!(index.ids_containing("aaaaa"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn multibyte_characters_are_matched_bytewise() {
+        let index = index(&["größe", "strasse", "straße"]);
+        assert_eq!(index.ids_containing("ß"), vec![0, 2]);
+        assert_eq!(index.ids_containing("öß"), vec![0]);
+    }
+}
@@ -0,0 +1,104 @@
+//! Recording when each value was first integerised.
+
+use std::time::SystemTime;
+
+use Integeriser;
+
+/// The moment at which a value was first integerised.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Insertion {
+    /// Number of calls of `integerise` that preceded the insertion.
+    pub seq: u64,
+    /// Wall clock time of the insertion.
+    pub time: SystemTime,
+}
+
+/// Integeriser that records the `Insertion` of every new value.
+/// Values that the inner integeriser already contains when it is wrapped
+/// have no recorded insertion.
+///
+/// # Example
+///
+/// ```
+/// use std::time::SystemTime;
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::timestamped::Timestamped;
+///
+/// let mut known = HashIntegeriser::new();
+/// known.integerise("<unk>");
+///
+/// let mut integeriser = Timestamped::new(known);
+/// for w in "the cat saw the dog".split(' ') {
+///     integeriser.integerise(w);
+/// }
+///
+/// assert_eq!(integeriser.inserted_at(0), None);
+/// assert_eq!(integeriser.inserted_at(4).unwrap().seq, 4);
+/// assert!(integeriser.inserted_at(1).unwrap().time <= SystemTime::now());
+///
+/// let start = integeriser.inserted_at(1).unwrap().time;
+/// assert!(integeriser.ids_inserted_after(start).all(|k| k > 1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Timestamped<I> {
+    inner: I,
+    offset: usize,
+    insertions: Vec<Insertion>,
+    seq: u64,
+}
+
+impl<I: Integeriser> Timestamped<I> {
+    /// Wraps `inner`, recording the insertions of values it does not contain yet.
+    pub fn new(inner: I) -> Self {
+        let offset = inner.size();
+        Timestamped { inner, offset, insertions: Vec::new(), seq: 0 }
+    }
+
+    /// The insertion of the value with the integer `k`, or `None` if `k` is
+    /// unknown or its value was contained in the inner integeriser before
+    /// it was wrapped.
+    pub fn inserted_at(&self, k: usize) -> Option<&Insertion> {
+        k.checked_sub(self.offset).and_then(|i| self.insertions.get(i))
+    }
+
+    /// Integers (in ascending order) of the values that were inserted strictly after `t`.
+    pub fn ids_inserted_after<'a>(&'a self, t: SystemTime) -> impl Iterator<Item = usize> + 'a {
+        let offset = self.offset;
+        self.insertions
+            .iter()
+            .enumerate()
+            .filter(move |&(_, insertion)| insertion.time > t)
+            .map(move |(i, _)| offset + i)
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Integeriser> Integeriser for Timestamped<I> {
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let size = self.inner.size();
+        let k = self.inner.integerise(a);
+        if self.inner.size() > size {
+            self.insertions.push(Insertion { seq: self.seq, time: SystemTime::now() });
+        }
+        self.seq += 1;
+        k
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        self.inner.find_key(a)
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
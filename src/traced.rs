@@ -0,0 +1,90 @@
+//! Reporting the growth of an integeriser as `tracing` events.
+
+use budget::{footprint, MemSize};
+use Integeriser;
+
+/// Integeriser that emits a `tracing` event with the number of values and
+/// their estimated footprint (see `budget::Budgeted`) whenever the number
+/// of values reaches a multiple of a given interval.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::{Integeriser, HashIntegeriser};
+/// use integeriser::traced::Traced;
+///
+/// let mut integeriser = Traced::new(HashIntegeriser::new(), 1000);
+/// for k in 0..2500 {
+///     integeriser.integerise(k % 2000);
+/// }
+///
+/// assert_eq!(integeriser.size(), 2000);
+/// assert!(integeriser.bytes() >= 2000 * 8);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Traced<I> {
+    inner: I,
+    bytes: usize,
+    interval: usize,
+}
+
+impl<I: Integeriser> Traced<I>
+where
+    I::Item: MemSize,
+{
+    /// Wraps `inner`, reporting every `interval` new values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is `0`.
+    pub fn new(inner: I, interval: usize) -> Self {
+        assert!(interval > 0, "the interval must be positive");
+        let bytes = (0..inner.size())
+            .filter_map(|k| inner.find_value(k))
+            .map(footprint)
+            .sum();
+        Traced { inner, bytes, interval }
+    }
+
+    /// Estimated number of bytes occupied by the values.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Unwraps the inner integeriser.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl<I: Integeriser> Integeriser for Traced<I>
+where
+    I::Item: MemSize,
+{
+    type Item = I::Item;
+
+    fn integerise(&mut self, a: I::Item) -> usize {
+        let bytes = footprint(&a);
+        let size = self.inner.size();
+        let k = self.inner.integerise(a);
+        if self.inner.size() > size {
+            self.bytes += bytes;
+            if self.inner.size().is_multiple_of(self.interval) {
+                tracing::info!(values = self.inner.size(), bytes = self.bytes, "integeriser grew");
+            }
+        }
+        k
+    }
+
+    fn find_value(&self, k: usize) -> Option<&I::Item> {
+        self.inner.find_value(k)
+    }
+
+    fn find_key(&self, a: &I::Item) -> Option<usize> {
+        self.inner.find_key(a)
+    }
+
+    fn size(&self) -> usize {
+        self.inner.size()
+    }
+}
@@ -0,0 +1,117 @@
+//! Integeriser that hands out shared handles to its values and can forget
+//! values that are no longer referenced anywhere else.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// Structure that maps to every element of type `A` an integer of type `usize`,
+/// given that `A: Eq + Hash`.  Mapping goes both ways.
+/// Values are handed out as `Arc<A>`; `collect` removes all values for which
+/// no handle exists outside of the integeriser, and their integers are
+/// reused for values that are integerised later.
+///
+/// Since integers are reused, this structure does not implement `Integeriser`.
+///
+/// # Example
+///
+/// ```
+/// use integeriser::weak::WeakIntegeriser;
+///
+/// let mut integeriser = WeakIntegeriser::new();
+///
+/// let (k, session) = integeriser.integerise("session-1".to_string());
+/// let (l, _) = integeriser.integerise("session-2".to_string());
+///
+/// assert_eq!(integeriser.collect(), vec![l]);
+/// assert_eq!(integeriser.find_key(&"session-1".to_string()), Some(k));
+/// assert_eq!(integeriser.find_value(l), None);
+///
+/// // the integer of the collected value is reused
+/// assert_eq!(integeriser.integerise("session-3".to_string()).0, l);
+///
+/// drop(session);
+/// assert_eq!(integeriser.collect(), vec![k, l]);
+/// assert_eq!(integeriser.size(), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct WeakIntegeriser<A: Eq + Hash> {
+    map: Vec<Option<Arc<A>>>,
+    rmap: HashMap<Arc<A>, usize>,
+    free: Vec<usize>,
+}
+
+impl<A: Eq + Hash> WeakIntegeriser<A> {
+    /// Constructs a new, empty `WeakIntegeriser<A>`.
+    pub fn new() -> Self {
+        WeakIntegeriser {
+            map: Vec::new(),
+            rmap: HashMap::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Returns a unique integer for the given value `a` and a handle to the
+    /// stored value.  The integer is the smallest unused one that has been
+    /// freed by `collect`, or the next consecutive one if there is none.
+    pub fn integerise(&mut self, a: A) -> (usize, Arc<A>) {
+        if let Some((value, &k)) = self.rmap.get_key_value(&a) {
+            return (k, value.clone());
+        }
+
+        let value = Arc::new(a);
+        let k = match self.free.pop() {
+            Some(k) => {
+                self.map[k] = Some(value.clone());
+                k
+            }
+            None => {
+                self.map.push(Some(value.clone()));
+                self.map.len() - 1
+            }
+        };
+        self.rmap.insert(value.clone(), k);
+        (k, value)
+    }
+
+    /// Lookup the value that corresponds to the integer `k: usize`.
+    pub fn find_value(&self, k: usize) -> Option<Arc<A>> {
+        self.map.get(k).and_then(|value| value.clone())
+    }
+
+    /// Lookup the integer that corresponds to the value `a: A`.
+    pub fn find_key(&self, a: &A) -> Option<usize> {
+        self.rmap.get(a).cloned()
+    }
+
+    /// Number of values that are currently stored in the integeriser.
+    pub fn size(&self) -> usize {
+        self.rmap.len()
+    }
+
+    /// Removes all values that are not referenced outside of the integeriser
+    /// and returns their integers in ascending order.
+    pub fn collect(&mut self) -> Vec<usize> {
+        let mut collected = Vec::new();
+        for (k, slot) in self.map.iter_mut().enumerate() {
+            // one reference is held by `map` and one by `rmap`
+            if slot.as_ref().is_some_and(|value| Arc::strong_count(value) == 2) {
+                if let Some(value) = slot.take() {
+                    self.rmap.remove(&value);
+                }
+                collected.push(k);
+            }
+        }
+
+        count!("integeriser_evictions_total", collected.len());
+        self.free.extend(collected.iter().cloned());
+        self.free.sort_unstable_by(|k, l| l.cmp(k));
+        collected
+    }
+}
+
+impl<A: Eq + Hash> Default for WeakIntegeriser<A> {
+    fn default() -> Self {
+        WeakIntegeriser::new()
+    }
+}